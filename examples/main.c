@@ -1,4 +1,5 @@
 #include "glass.h"
+#include <inttypes.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
@@ -28,9 +29,24 @@ void print_value(const GlassValue *value, int indent) {
   case GLASS_NUMBER:
     printf("%g", glass_value_get_number(value));
     break;
+  case GLASS_INT:
+    printf("%" PRId64, glass_value_get_int(value));
+    break;
+  case GLASS_UINT:
+    printf("%" PRIu64, glass_value_get_uint(value));
+    break;
   case GLASS_STRING:
     print_string(glass_value_get_string(value));
     break;
+  case GLASS_BYTES: {
+    size_t len;
+    const uint8_t *bytes = glass_value_get_bytes(value, &len);
+    printf("b\"");
+    for (size_t i = 0; i < len; i++)
+      printf("\\x%02x", bytes[i]);
+    printf("\"");
+    break;
+  }
   case GLASS_ARRAY: {
     const GlassArray *arr = glass_value_get_array(value);
     printf("[\n");
@@ -69,6 +85,7 @@ int main(void) {
   const char *input = "root {\n"
                       "  name \"Alice\",\n"
                       "  age 30,\n"
+                      "  avatar b\"\\x89PNG\",\n"
                       "  hobbies [\n"
                       "    \"reading\",\n"
                       "    \"coding\",\n"
@@ -16,9 +16,16 @@ typedef enum {
   GLASS_NUMBER = 2,
   GLASS_STRING = 3,
   GLASS_ARRAY = 4,
-  GLASS_MAP = 5
+  GLASS_MAP = 5,
+  /* An integer that fits in an int64_t. */
+  GLASS_INT = 6,
+  /* An integer above INT64_MAX that fits in a uint64_t. */
+  GLASS_UINT = 7,
+  /* A byte string, which may contain NUL bytes. */
+  GLASS_BYTES = 8
 } GlassValueKind;
 
+typedef struct GlassBytes GlassBytes;
 typedef struct GlassArray GlassArray;
 typedef struct GlassMap GlassMap;
 
@@ -27,12 +34,20 @@ typedef struct {
   union {
     bool bool_val;
     double number_val;
+    int64_t int_val;
+    uint64_t uint_val;
     char *string_val;
+    GlassBytes *bytes_val;
     GlassArray *array_val;
     GlassMap *map_val;
   } data;
 } GlassValue;
 
+typedef struct GlassBytes {
+  size_t len;
+  uint8_t *data;
+} GlassBytes;
+
 typedef struct GlassArray {
   size_t len;
   GlassValue *data;
@@ -82,13 +97,24 @@ GlassValueKind glass_value_get_kind(const GlassValue *ptr);
 /* ptr must be non-NULL, valid, and kind must be GLASS_BOOL.
    Returns false if ptr is NULL. */
 bool glass_value_get_bool(const GlassValue *ptr);
-/* ptr must be non-NULL, valid, and kind must be GLASS_NUMBER.
+/* ptr must be non-NULL, valid, and kind must be GLASS_NUMBER. GLASS_NUMBER
+   holds floats, integers are GLASS_INT or GLASS_UINT.
    Returns DBL_MAX if ptr is NULL. */
 double glass_value_get_number(const GlassValue *ptr);
+/* ptr must be non-NULL, valid, and kind must be GLASS_INT.
+   Returns INT64_MAX if ptr is NULL. */
+int64_t glass_value_get_int(const GlassValue *ptr);
+/* ptr must be non-NULL, valid, and kind must be GLASS_UINT.
+   Returns UINT64_MAX if ptr is NULL. */
+uint64_t glass_value_get_uint(const GlassValue *ptr);
 /* ptr must be non-NULL, valid, and kind must be GLASS_STRING.
    Returned pointer is valid until the owning GlassResult is freed.
    Returns NULL if ptr is NULL. */
 const char *glass_value_get_string(const GlassValue *ptr);
+/* ptr must be non-NULL, valid, and kind must be GLASS_BYTES. len may be NULL,
+   otherwise the number of bytes is stored in it. Returned pointer is valid
+   until the owning GlassResult is freed. Returns NULL if ptr is NULL. */
+const uint8_t *glass_value_get_bytes(const GlassValue *ptr, size_t *len);
 /* ptr must be non-NULL, valid, and kind must be GLASS_ARRAY.
    Returns NULL if ptr is NULL. */
 const GlassArray *glass_value_get_array(const GlassValue *ptr);
@@ -0,0 +1,425 @@
+//! [`Map`], the entries of a [`Value::Map`], and its entry API.
+
+use indexmap::IndexMap;
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops;
+
+use super::Value;
+
+/// The entries of a [`Value::Map`] in insertion order, which for a parsed map is the order
+/// they appear in the file. Lookups by key are hashed.
+///
+/// Two maps are equal if they have the same entries in the same order.
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: IndexMap<Box<str>, Value>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Map::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Map {
+            entries: IndexMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns the value of `key` as stored, including any [`Value::Commented`] wrapper.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries.get_mut(key)
+    }
+
+    /// Returns the entry at `index` in iteration order.
+    pub fn get_index(&self, index: usize) -> Option<(&str, &Value)> {
+        self.entries
+            .get_index(index)
+            .map(|(key, value)| (&**key, value))
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&str, &mut Value)> {
+        self.entries
+            .get_index_mut(index)
+            .map(|(key, value)| (&**key, value))
+    }
+
+    /// Returns the position of `key` in iteration order.
+    pub fn get_index_of(&self, key: &str) -> Option<usize> {
+        self.entries.get_index_of(key)
+    }
+
+    /// Inserts an entry, returning the old value if `key` was already present. An existing
+    /// entry keeps its position, a new one is appended.
+    pub fn insert(&mut self, key: impl Into<Box<str>>, value: Value) -> Option<Value> {
+        self.entries.insert(key.into(), value)
+    }
+
+    /// Inserts an entry at `index`, moving an existing entry for `key` there. Returns the old
+    /// value if `key` was already present.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is greater than the length of the map.
+    pub fn insert_at(
+        &mut self,
+        index: usize,
+        key: impl Into<Box<str>>,
+        value: Value,
+    ) -> Option<Value> {
+        self.entries.shift_insert(index, key.into(), value)
+    }
+
+    /// Removes an entry, shifting the ones after it to keep their order. This takes time
+    /// linear in the number of entries after it.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.entries.shift_remove(key)
+    }
+
+    pub fn remove_entry(&mut self, key: &str) -> Option<(Box<str>, Value)> {
+        self.entries.shift_remove_entry(key)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&str, &mut Value) -> bool) {
+        self.entries.retain(|key, value| keep(key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn entry(&mut self, key: impl Into<Box<str>>) -> Entry<'_> {
+        match self.entries.entry(key.into()) {
+            indexmap::map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry { entry }),
+            indexmap::map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry { entry }),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            iter: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            iter: self.entries.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.entries.keys().map(|key| &**key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &Value> + ExactSizeIterator {
+        self.entries.values()
+    }
+
+    pub fn values_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut Value> + ExactSizeIterator {
+        self.entries.values_mut()
+    }
+}
+
+/// # Panics
+///
+/// If `key` isn't in the map.
+impl ops::Index<&str> for Map {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self.entries.get(key) {
+            Some(value) => value,
+            None => panic!("no entry for `{key}` in the map"),
+        }
+    }
+}
+
+impl ops::IndexMut<&str> for Map {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        match self.entries.get_mut(key) {
+            Some(value) => value,
+            None => panic!("no entry for `{key}` in the map"),
+        }
+    }
+}
+
+impl PartialEq for Map {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Later entries replace the values of earlier ones with the same key, see [`Map::insert`].
+impl FromIterator<(Box<str>, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (Box<str>, Value)>>(iter: I) -> Self {
+        Map {
+            entries: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<const N: usize> From<[(Box<str>, Value); N]> for Map {
+    fn from(entries: [(Box<str>, Value); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl Extend<(Box<str>, Value)> for Map {
+    fn extend<I: IntoIterator<Item = (Box<str>, Value)>>(&mut self, iter: I) {
+        self.entries.extend(iter);
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (Box<str>, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            iter: self.entries.into_iter(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a str, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map {
+    type Item = (&'a str, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Iterator over the entries of a [`Map`] in order.
+pub struct Iter<'a> {
+    iter: indexmap::map::Iter<'a, Box<str>, Value>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (&**key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, value)| (&**key, value))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// Iterator over the entries of a [`Map`] in order, with mutable values.
+pub struct IterMut<'a> {
+    iter: indexmap::map::IterMut<'a, Box<str>, Value>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a str, &'a mut Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (&**key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IterMut<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, value)| (&**key, value))
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+/// Owning iterator over the entries of a [`Map`] in order.
+pub struct IntoIter {
+    iter: indexmap::map::IntoIter<Box<str>, Value>,
+}
+
+impl Iterator for IntoIter {
+    type Item = (Box<str>, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+/// An entry of a [`Map`], created by [`Map::entry`].
+pub enum Entry<'a> {
+    Vacant(VacantEntry<'a>),
+    Occupied(OccupiedEntry<'a>),
+}
+
+pub struct VacantEntry<'a> {
+    entry: indexmap::map::VacantEntry<'a, Box<str>, Value>,
+}
+
+pub struct OccupiedEntry<'a> {
+    entry: indexmap::map::OccupiedEntry<'a, Box<str>, Value>,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &str {
+        match self {
+            Entry::Vacant(entry) => entry.key(),
+            Entry::Occupied(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with(self, default: impl FnOnce() -> Value) -> &'a mut Value {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn and_modify(mut self, modify: impl FnOnce(&mut Value)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            modify(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn key(&self) -> &str {
+        self.entry.key()
+    }
+
+    /// Appends the entry to the map.
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        self.entry.insert(value)
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    pub fn key(&self) -> &str {
+        self.entry.key()
+    }
+
+    pub fn get(&self) -> &Value {
+        self.entry.get()
+    }
+
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.entry.get_mut()
+    }
+
+    pub fn into_mut(self) -> &'a mut Value {
+        self.entry.into_mut()
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: Value) -> Value {
+        self.entry.insert(value)
+    }
+
+    /// Removes the entry, shifting the ones after it like [`Map::remove`].
+    pub fn remove(self) -> Value {
+        self.entry.shift_remove()
+    }
+}
+
+impl Serialize for Map {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Map {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MapVisitor;
+
+        impl<'de> Visitor<'de> for MapVisitor {
+            type Value = Map;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a glass map")
+            }
+
+            fn visit_map<A>(self, mut access: A) -> Result<Map, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut map = Map::with_capacity(access.size_hint().unwrap_or(0));
+                while let Some((key, value)) = access.next_entry::<Box<str>, Value>()? {
+                    map.insert(key, value);
+                }
+                Ok(map)
+            }
+        }
+
+        deserializer.deserialize_map(MapVisitor)
+    }
+}
@@ -1,18 +1,443 @@
 use thin_vec::ThinVec;
 
 use serde::de::{self, MapAccess, SeqAccess, Visitor};
-use serde::ser::{SerializeMap, SerializeSeq};
+use serde::ser::{self, SerializeMap, SerializeSeq};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
+use std::ops;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, PartialEq)]
+pub use map::Map;
+pub use number::Number;
+
+pub mod map;
+mod number;
+
+/// Newtype struct name [`Value::Commented`] serializes through. The glass serializer recognizes
+/// it and writes the comments stashed in [`PENDING_COMMENTS`], every other serializer sees a
+/// transparent newtype.
+pub(crate) const COMMENTED: &str = "$glass::private::Commented";
+
+thread_local! {
+    static PENDING_COMMENTS: RefCell<Option<(ThinVec<Comment>, ThinVec<Comment>)>> =
+        const { RefCell::new(None) };
+}
+
+/// Takes the leading and trailing comments of the [`Value::Commented`] currently being
+/// serialized.
+pub(crate) fn take_pending_comments() -> Option<(ThinVec<Comment>, ThinVec<Comment>)> {
+    PENDING_COMMENTS.with_borrow_mut(Option::take)
+}
+
+/// Any glass value.
+///
+/// The accessors and indexing look through [`Value::Commented`] wrappers, so a value parsed with
+/// comments preserved reads the same as one without. Build values with the `From` impls or the
+/// [`glass!`](crate::glass) macro.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum Value {
-    Map(ThinVec<(Box<str>, Value)>),
+    Map(Map),
     Array(ThinVec<Value>),
     String(String),
-    Number(f64),
+    /// A `b"..."` byte string.
+    Bytes(Vec<u8>),
+    Number(Number),
     Bool(bool),
+    /// `null`, also used for `None` and `()`.
+    #[default]
+    Null,
+    /// A map value or array element together with the comments around it. Only produced when
+    /// comments are preserved, see [`ParseOptions::preserve_comments`](crate::ParseOptions).
+    Commented(Box<Commented>),
+    /// Stands in for a value that failed to parse, see
+    /// [`parse_value_recovering`](crate::parse_value_recovering). It can't be serialized or
+    /// deserialized from.
+    Invalid,
+}
+
+/// Shared by the `Index` impls for keys and positions that aren't there.
+static NULL: Value = Value::Null;
+
+impl Value {
+    /// Returns the value with any [`Value::Commented`] wrappers removed.
+    pub(crate) fn uncommented(self) -> Value {
+        match self {
+            Value::Commented(commented) => commented.value.uncommented(),
+            value => value,
+        }
+    }
+
+    /// The value inside any [`Value::Commented`] wrappers.
+    pub(crate) fn inner(&self) -> &Value {
+        match self {
+            Value::Commented(commented) => commented.value.inner(),
+            value => value,
+        }
+    }
+
+    pub(crate) fn inner_mut(&mut self) -> &mut Value {
+        match self {
+            Value::Commented(commented) => commented.value.inner_mut(),
+            value => value,
+        }
+    }
+
+    /// Returns the value of `key` if this is a map that has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_map()?.get(key).map(Value::inner)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_map_mut()?.get_mut(key).map(Value::inner_mut)
+    }
+
+    pub fn as_map(&self) -> Option<&Map> {
+        match self.inner() {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Map> {
+        match self.inner_mut() {
+            Value::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&ThinVec<Value>> {
+        match self.inner() {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut ThinVec<Value>> {
+        match self.inner_mut() {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.inner() {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self.inner_mut() {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.inner() {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self.inner_mut() {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self.inner() {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_number_mut(&mut self) -> Option<&mut Number> {
+        match self.inner_mut() {
+            Value::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as an `i64` if it is an integer in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// Returns the number as a `u64` if it is an integer in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    /// Returns any number as a float, see [`Number::as_f64`].
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(Number::as_f64)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.inner() {
+            Value::Bool(bool) => Some(*bool),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self.inner_mut() {
+            Value::Bool(bool) => Some(bool),
+            _ => None,
+        }
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self.inner(), Value::Map(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.inner(), Value::Array(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self.inner(), Value::String(_))
+    }
+
+    pub fn is_bytes(&self) -> bool {
+        matches!(self.inner(), Value::Bytes(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self.inner(), Value::Number(_))
+    }
+
+    /// Whether this is a number that fits an `i64`.
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    /// Whether this is a number that fits a `u64`.
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    pub fn is_f64(&self) -> bool {
+        self.as_number().is_some_and(Number::is_f64)
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self.inner(), Value::Bool(_))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.inner(), Value::Null)
+    }
+}
+
+/// Returns [`Value::Null`] for missing keys and anything that isn't a map.
+impl ops::Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// Appends a `null` entry for missing keys and turns a `null` into an empty map first, so
+/// `value["a"]["b"] = 1.into()` builds nested maps.
+///
+/// # Panics
+///
+/// If the value is neither a map nor `null`.
+impl ops::IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        let value = self.inner_mut();
+        if let Value::Null = value {
+            *value = Value::Map(Map::new());
+        }
+        let Value::Map(map) = value else {
+            panic!("cannot index into a non-map value with `{key}`");
+        };
+        map.entry(key).or_insert(Value::Null).inner_mut()
+    }
+}
+
+/// Returns [`Value::Null`] for positions out of bounds and anything that isn't an array.
+impl ops::Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        self.as_array()
+            .and_then(|array| array.get(index))
+            .map_or(&NULL, Value::inner)
+    }
+}
+
+/// # Panics
+///
+/// If the value isn't an array or `index` is out of bounds.
+impl ops::IndexMut<usize> for Value {
+    fn index_mut(&mut self, index: usize) -> &mut Value {
+        let Some(array) = self.as_array_mut() else {
+            panic!("cannot index into a non-array value with {index}");
+        };
+        let len = array.len();
+        match array.get_mut(index) {
+            Some(value) => value.inner_mut(),
+            None => panic!("index {index} is out of bounds for an array of length {len}"),
+        }
+    }
+}
+
+macro_rules! from_number {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(number: $ty) -> Self {
+                    Value::Number(number.into())
+                }
+            }
+        )*
+    };
+}
+
+from_number!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 usize f32 f64 Number);
+
+impl From<bool> for Value {
+    fn from(bool: bool) -> Self {
+        Value::Bool(bool)
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::String(string)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value::String(string.to_owned())
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(string: Cow<'_, str>) -> Self {
+        Value::String(string.into_owned())
+    }
+}
+
+impl From<()> for Value {
+    fn from((): ()) -> Self {
+        Value::Null
+    }
+}
+
+/// `None` becomes [`Value::Null`].
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Value::Null, Into::into)
+    }
+}
+
+/// Builds an array. Use [`Value::Bytes`] directly for a byte string.
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(vec: Vec<T>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(slice: &[T]) -> Self {
+        slice.iter().cloned().collect()
+    }
+}
+
+impl<T: Into<Value>, const N: usize> From<[T; N]> for Value {
+    fn from(array: [T; N]) -> Self {
+        array.into_iter().collect()
+    }
+}
+
+/// Builds a map. Its entries are in the map's iteration order, which for a `HashMap` is
+/// unspecified.
+impl<K: Into<Box<str>>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    fn from(map: HashMap<K, V, S>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<K: Into<Box<str>>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Value::Array(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<Box<str>>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Map(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commented {
+    /// Comments on the lines before the entry.
+    pub leading: ThinVec<Comment>,
+    pub value: Value,
+    /// Comments after the entry's comma on the same line, or before the closing delimiter when
+    /// this is the last entry.
+    pub trailing: ThinVec<Comment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// ...` up to the end of the line.
+    Line,
+    /// `/* ... */`
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// The comment text without its delimiters.
+    pub text: String,
+}
+
+impl Comment {
+    pub fn line(text: impl Into<String>) -> Self {
+        Comment {
+            kind: CommentKind::Line,
+            text: text.into(),
+        }
+    }
+
+    pub fn block(text: impl Into<String>) -> Self {
+        Comment {
+            kind: CommentKind::Block,
+            text: text.into(),
+        }
+    }
+}
+
+impl fmt::Display for Comment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            CommentKind::Line => write!(f, "//{}", self.text),
+            CommentKind::Block => write!(f, "/*{}*/", self.text),
+        }
+    }
 }
 
 impl Serialize for Value {
@@ -21,13 +446,10 @@ impl Serialize for Value {
         S: Serializer,
     {
         match self {
-            Value::Map(map) if map.len() == 1 && map[0].0.as_ref() == "root" => {
-                map[0].1.serialize(serializer)
-            }
             Value::Map(map) => {
                 let mut map_ser = serializer.serialize_map(Some(map.len()))?;
-                for (key, value) in map.iter() {
-                    map_ser.serialize_entry(key.as_ref(), value)?;
+                for (key, value) in map {
+                    map_ser.serialize_entry(key, value)?;
                 }
                 map_ser.end()
             }
@@ -39,8 +461,20 @@ impl Serialize for Value {
                 seq.end()
             }
             Value::String(s) => serializer.serialize_str(s),
-            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::Bytes(b) => serializer.serialize_bytes(b),
+            Value::Number(n) => n.serialize(serializer),
             Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Null => serializer.serialize_unit(),
+            Value::Commented(commented) => {
+                PENDING_COMMENTS.set(Some((
+                    commented.leading.clone(),
+                    commented.trailing.clone(),
+                )));
+                let result = serializer.serialize_newtype_struct(COMMENTED, &commented.value);
+                PENDING_COMMENTS.set(None);
+                result
+            }
+            Value::Invalid => Err(ser::Error::custom("cannot serialize an invalid value")),
         }
     }
 }
@@ -66,11 +500,63 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Bool(value))
             }
 
+            fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Number(value.into()))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Number(value.into()))
+            }
+
+            fn visit_i128<E>(self, value: i128) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Number(value.into()))
+            }
+
+            fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                match i128::try_from(value) {
+                    Ok(value) => Ok(Value::Number(value.into())),
+                    Err(_) => Err(E::custom(format!("integer {value} is out of range"))),
+                }
+            }
+
             fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
             where
                 E: de::Error,
             {
-                Ok(Value::Number(value))
+                Ok(Value::Number(value.into()))
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Null)
+            }
+
+            fn visit_none<E>(self) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Null)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Deserialize::deserialize(deserializer)
             }
 
             fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
@@ -87,6 +573,20 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::String(value))
             }
 
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Bytes(value.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Bytes(value))
+            }
+
             fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
             where
                 A: SeqAccess<'de>,
@@ -102,11 +602,11 @@ impl<'de> Deserialize<'de> for Value {
             where
                 A: MapAccess<'de>,
             {
-                let mut vec = ThinVec::new();
-                while let Some((key, value)) = map.next_entry()? {
-                    vec.push((key, value));
+                let mut entries = Map::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry::<Box<str>, Value>()? {
+                    entries.insert(key, value);
                 }
-                Ok(Value::Map(vec))
+                Ok(Value::Map(entries))
             }
         }
 
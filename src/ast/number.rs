@@ -0,0 +1,248 @@
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// A glass number, either an integer or a float.
+///
+/// Integer literals keep their exact value across the whole `i64` and `u64` ranges, and beyond up
+/// to `i128`. Only literals with a decimal point or an exponent, `inf` and `nan` are floats.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Number(N);
+
+#[derive(Clone, Copy, PartialEq)]
+enum N {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    /// Parses a number literal as produced by the lexer, returning `None` for malformed ones.
+    ///
+    /// Integer literals, including `0x`, `0o` and `0b` ones, must be in the `i128` range. Decimal
+    /// literals with a fraction or an exponent, and `inf` and `nan`, are floats. Digits may be
+    /// separated by single `_`s.
+    pub(crate) fn parse(literal: &str) -> Option<Number> {
+        let (sign, unsigned) = match literal.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", literal.strip_prefix('+').unwrap_or(literal)),
+        };
+
+        let radix = match unsigned.get(..2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => 10,
+        };
+        // Separators only go between two digits, not next to a prefix, sign, point or exponent.
+        let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_digit(radix));
+        for (i, _) in unsigned.match_indices('_') {
+            if !is_digit(unsigned[..i].chars().next_back())
+                || !is_digit(unsigned[i + 1..].chars().next())
+            {
+                return None;
+            }
+        }
+        let unsigned = unsigned.replace('_', "");
+        let unsigned = unsigned.as_str();
+        let literal = &format!("{sign}{unsigned}");
+
+        if radix != 10 {
+            let digits = &unsigned[2..];
+            if !digits.chars().all(|c| c.is_digit(radix)) {
+                return None;
+            }
+            let int = i128::from_str_radix(&format!("{sign}{digits}"), radix).ok()?;
+            return Some(Number(N::Int(int)));
+        }
+
+        if !matches!(unsigned, "inf" | "nan")
+            && !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        {
+            return None;
+        }
+        if unsigned.bytes().all(|b| b.is_ascii_digit()) {
+            return literal.parse().ok().map(|int| Number(N::Int(int)));
+        }
+        literal.parse().ok().map(|float| Number(N::Float(float)))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self.0, N::Int(_))
+    }
+
+    pub fn is_f64(&self) -> bool {
+        matches!(self.0, N::Float(_))
+    }
+
+    /// Returns the number as an `i64` if it is an integer in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128().and_then(|int| int.try_into().ok())
+    }
+
+    /// Returns the number as a `u64` if it is an integer in range.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128().and_then(|int| int.try_into().ok())
+    }
+
+    /// Returns the number if it is an integer.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.0 {
+            N::Int(int) => Some(int),
+            N::Float(_) => None,
+        }
+    }
+
+    /// Returns the number as a float, rounding integers that have no exact `f64` representation.
+    pub fn as_f64(&self) -> f64 {
+        match self.0 {
+            N::Int(int) => int as f64,
+            N::Float(float) => float,
+        }
+    }
+
+    /// Calls the narrowest `visit_*` method of `visitor` that holds the number exactly.
+    pub(crate) fn visit<'de, V, E>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+        E: de::Error,
+    {
+        match self.0 {
+            N::Int(int) => {
+                if let Ok(int) = i64::try_from(int) {
+                    visitor.visit_i64(int)
+                } else if let Ok(int) = u64::try_from(int) {
+                    visitor.visit_u64(int)
+                } else {
+                    visitor.visit_i128(int)
+                }
+            }
+            N::Float(float) => visitor.visit_f64(float),
+        }
+    }
+}
+
+macro_rules! from_int {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(int: $ty) -> Self {
+                    Number(N::Int(int.into()))
+                }
+            }
+        )*
+    };
+}
+
+from_int!(i8 i16 i32 i64 i128 u8 u16 u32 u64);
+
+impl From<isize> for Number {
+    fn from(int: isize) -> Self {
+        Number(N::Int(int as i128))
+    }
+}
+
+impl From<usize> for Number {
+    fn from(int: usize) -> Self {
+        Number(N::Int(int as i128))
+    }
+}
+
+impl From<f32> for Number {
+    fn from(float: f32) -> Self {
+        Number(N::Float(float.into()))
+    }
+}
+
+impl From<f64> for Number {
+    fn from(float: f64) -> Self {
+        Number(N::Float(float))
+    }
+}
+
+/// Writes numbers as glass literals. Floats always get a decimal point or an exponent so they
+/// read back as floats, very large and very small ones use exponent notation.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            N::Int(int) => write!(f, "{int}"),
+            N::Float(float) if float.is_nan() => write!(f, "nan"),
+            N::Float(float) if float.is_infinite() => write!(f, "{float}"),
+            N::Float(float) if float != 0.0 && !(1e-5..1e16).contains(&float.abs()) => {
+                write!(f, "{float:e}")
+            }
+            N::Float(float) if float.fract() == 0.0 => write!(f, "{float:.1}"),
+            N::Float(float) => write!(f, "{float}"),
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Number({self})")
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            N::Int(int) => {
+                if let Ok(int) = i64::try_from(int) {
+                    serializer.serialize_i64(int)
+                } else if let Ok(int) = u64::try_from(int) {
+                    serializer.serialize_u64(int)
+                } else {
+                    serializer.serialize_i128(int)
+                }
+            }
+            N::Float(float) => serializer.serialize_f64(float),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NumberVisitor;
+
+        impl Visitor<'_> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a number")
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Number, E> {
+                Ok(value.into())
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Number, E> {
+                Ok(value.into())
+            }
+
+            fn visit_i128<E>(self, value: i128) -> Result<Number, E> {
+                Ok(value.into())
+            }
+
+            fn visit_u128<E>(self, value: u128) -> Result<Number, E>
+            where
+                E: de::Error,
+            {
+                match i128::try_from(value) {
+                    Ok(value) => Ok(value.into()),
+                    Err(_) => Err(E::custom(format!("integer {value} is out of range"))),
+                }
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Number, E> {
+                Ok(value.into())
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
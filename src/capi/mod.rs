@@ -1,6 +1,6 @@
 #![allow(unsafe_op_in_unsafe_fn)]
 
-use crate::ast::Value;
+use crate::ast::{Map, Value};
 use crate::de;
 use crate::ser;
 use crate::{Error, Result};
@@ -19,6 +19,9 @@ pub enum CValueKind {
     String = 3,
     Array = 4,
     Map = 5,
+    Int = 6,
+    UInt = 7,
+    Bytes = 8,
 }
 
 #[repr(C)]
@@ -33,11 +36,21 @@ pub struct CValue {
 pub union CValueData {
     pub bool_val: bool,
     pub number_val: f64,
+    pub int_val: i64,
+    pub uint_val: u64,
     pub string_val: *mut c_char,
+    pub bytes_val: *mut CValueBytes,
     pub array_val: *mut CValueArray,
     pub map_val: *mut CValueMap,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct CValueBytes {
+    pub len: usize,
+    pub data: *mut u8,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct CValueArray {
@@ -151,6 +164,13 @@ fn deallocate_string(s: *mut c_char) {
     }
 }
 
+fn bytes_layout(len: usize) -> Result<(Layout, usize)> {
+    let (layout, offset) = Layout::new::<CValueBytes>()
+        .extend(Layout::array::<u8>(len).map_err(|e| Error::CApi(e.to_string()))?)
+        .map_err(|e| Error::CApi(e.to_string()))?;
+    Ok((layout.pad_to_align(), offset))
+}
+
 fn array_layout(len: usize) -> Result<(Layout, usize)> {
     let (layout, offset) = Layout::new::<CValueArray>()
         .extend(Layout::array::<CValue>(len).map_err(|e| Error::CApi(e.to_string()))?)
@@ -168,18 +188,40 @@ fn map_layout(len: usize) -> Result<(Layout, usize)> {
 fn write_cvalue_in_place(ptr: *mut CValue, value: Value) -> Result<()> {
     unsafe {
         match value {
+            Value::Invalid => {
+                return Err(Error::CApi("cannot convert an invalid value".to_owned()));
+            }
+            Value::Null => {
+                (*ptr).kind = CValueKind::Null;
+                (*ptr).data.string_val = ptr::null_mut();
+            }
             Value::Bool(b) => {
                 (*ptr).kind = CValueKind::Bool;
                 (*ptr).data.bool_val = b;
             }
-            Value::Number(n) => {
+            Value::Number(n) if n.is_f64() => {
                 (*ptr).kind = CValueKind::Number;
-                (*ptr).data.number_val = n;
+                (*ptr).data.number_val = n.as_f64();
+            }
+            Value::Number(n) => {
+                if let Some(int) = n.as_i64() {
+                    (*ptr).kind = CValueKind::Int;
+                    (*ptr).data.int_val = int;
+                } else if let Some(uint) = n.as_u64() {
+                    (*ptr).kind = CValueKind::UInt;
+                    (*ptr).data.uint_val = uint;
+                } else {
+                    return Err(Error::CApi(format!("integer {n} doesn't fit in 64 bits")));
+                }
             }
             Value::String(s) => {
                 (*ptr).kind = CValueKind::String;
                 (*ptr).data.string_val = allocate_string(&s);
             }
+            Value::Bytes(bytes) => {
+                (*ptr).kind = CValueKind::Bytes;
+                (*ptr).data.bytes_val = allocate_bytes(&bytes)?;
+            }
             Value::Array(arr) => {
                 let array_ptr = allocate_array(arr.len())?;
                 (*ptr).kind = CValueKind::Array;
@@ -200,6 +242,9 @@ fn write_cvalue_in_place(ptr: *mut CValue, value: Value) -> Result<()> {
                     write_cvalue_in_place(&mut (*entry_ptr).value, val)?;
                 }
             }
+            Value::Commented(commented) => {
+                write_cvalue_in_place(ptr, commented.value)?;
+            }
         }
     }
     Ok(())
@@ -220,6 +265,23 @@ fn value_to_cvalue(value: Value) -> Result<*mut CValue> {
     }
 }
 
+fn allocate_bytes(bytes: &[u8]) -> Result<*mut CValueBytes> {
+    unsafe {
+        let (layout, offset) = bytes_layout(bytes.len())?;
+        let ptr = alloc(layout) as *mut CValueBytes;
+        if ptr.is_null() {
+            return Err(Error::CApi("out of memory".to_string()));
+        }
+
+        ptr::write(&mut (*ptr).len, bytes.len());
+        let data_ptr = (ptr as *mut u8).add(offset);
+        ptr::copy_nonoverlapping(bytes.as_ptr(), data_ptr, bytes.len());
+        ptr::write(&mut (*ptr).data, data_ptr);
+
+        Ok(ptr)
+    }
+}
+
 fn allocate_array(len: usize) -> Result<*mut CValueArray> {
     unsafe {
         let (layout, offset) = array_layout(len)?;
@@ -283,11 +345,23 @@ fn cvalue_to_value(ptr: *const CValue) -> Result<Value> {
     unsafe {
         Ok(match (*ptr).kind {
             CValueKind::Bool => Value::Bool((*ptr).data.bool_val),
-            CValueKind::Number => Value::Number((*ptr).data.number_val),
+            CValueKind::Number => Value::Number((*ptr).data.number_val.into()),
+            CValueKind::Int => Value::Number((*ptr).data.int_val.into()),
+            CValueKind::UInt => Value::Number((*ptr).data.uint_val.into()),
             CValueKind::String => {
                 let c_str = (*ptr).data.string_val;
                 Value::String(CStr::from_ptr(c_str).to_string_lossy().to_string())
             }
+            CValueKind::Bytes => {
+                let bytes_ptr = (*ptr).data.bytes_val;
+                let len = (*bytes_ptr).len;
+                let data = if len == 0 {
+                    &[]
+                } else {
+                    std::slice::from_raw_parts((*bytes_ptr).data, len)
+                };
+                Value::Bytes(data.to_vec())
+            }
             CValueKind::Array => {
                 let array_ptr = (*ptr).data.array_val;
                 let len = (*array_ptr).len;
@@ -301,18 +375,16 @@ fn cvalue_to_value(ptr: *const CValue) -> Result<Value> {
             CValueKind::Map => {
                 let map_ptr = (*ptr).data.map_val;
                 let len = (*map_ptr).len;
-                let mut vec = thin_vec::ThinVec::with_capacity(len);
+                let mut map = Map::with_capacity(len);
                 for i in 0..len {
                     let entry = &*(*map_ptr).entries.add(i);
-                    let key = Box::from(CStr::from_ptr(entry.key).to_string_lossy().to_string());
+                    let key = CStr::from_ptr(entry.key).to_string_lossy().to_string();
                     let value = cvalue_to_value(&entry.value)?;
-                    vec.push((key, value));
+                    map.insert(key, value);
                 }
-                Value::Map(vec)
-            }
-            CValueKind::Null => {
-                return Err(Error::CApi("Serializing null is not supported".to_string()));
+                Value::Map(map)
             }
+            CValueKind::Null => Value::Null,
         })
     }
 }
@@ -379,6 +451,30 @@ pub unsafe extern "C" fn glass_value_get_number(ptr: *const CValue) -> f64 {
     (*ptr).data.number_val
 }
 
+/// # Safety
+///
+/// `ptr` must be non-null and point to a valid `CValue` whose `kind` is `Int`. Returns i64::MAX if
+/// ptr is null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn glass_value_get_int(ptr: *const CValue) -> i64 {
+    if ptr.is_null() {
+        return i64::MAX;
+    }
+    (*ptr).data.int_val
+}
+
+/// # Safety
+///
+/// `ptr` must be non-null and point to a valid `CValue` whose `kind` is `UInt`. Returns u64::MAX
+/// if ptr is null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn glass_value_get_uint(ptr: *const CValue) -> u64 {
+    if ptr.is_null() {
+        return u64::MAX;
+    }
+    (*ptr).data.uint_val
+}
+
 /// # Safety
 ///
 /// `ptr` must be non-null and point to a valid `CValue` whose `kind` is `String`. The returned
@@ -391,6 +487,23 @@ pub unsafe extern "C" fn glass_value_get_string(ptr: *const CValue) -> *const c_
     (*ptr).data.string_val
 }
 
+/// # Safety
+///
+/// `ptr` must be non-null and point to a valid `CValue` whose `kind` is `Bytes`. `len` may be
+/// null, otherwise the number of bytes is written to it. The returned pointer is valid until the
+/// owning [`CResult`] is freed via [`glass_result_free`]. Returns null if ptr is null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn glass_value_get_bytes(ptr: *const CValue, len: *mut usize) -> *const u8 {
+    if ptr.is_null() {
+        return std::ptr::null();
+    }
+    let bytes = (*ptr).data.bytes_val;
+    if !len.is_null() {
+        *len = (*bytes).len;
+    }
+    (*bytes).data
+}
+
 /// # Safety
 ///
 /// `ptr` must be non-null and point to a valid `CValue` whose `kind` is `Array`.
@@ -541,6 +654,14 @@ fn free_cvalue_contents(ptr: *mut CValue) {
             CValueKind::String => {
                 deallocate_string((*ptr).data.string_val);
             }
+            CValueKind::Bytes => {
+                let bytes_ptr = (*ptr).data.bytes_val;
+                if bytes_ptr.is_null() {
+                    return;
+                }
+                let (layout, _) = bytes_layout((*bytes_ptr).len).expect("consistent layout");
+                dealloc(bytes_ptr as *mut u8, layout);
+            }
             CValueKind::Array => {
                 let array_ptr = (*ptr).data.array_val;
                 if array_ptr.is_null() {
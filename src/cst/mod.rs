@@ -0,0 +1,687 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::ast::{Number, Value};
+use crate::error::{Error, Result};
+use crate::lexer::{SpannedToken, Token, tokenize};
+use crate::parser::{ParseOptions, Root, parse};
+use crate::ser::{NewlineStyle, PrettyConfig, key_literal, to_string_pretty};
+
+/// A glass document that remembers exactly how it was written, for editing it in place.
+///
+/// Whitespace, comments, key quoting and number formats all survive [`Document::to_string`].
+/// [`Document::set`], [`Document::insert`] and [`Document::remove`] only rewrite the entries they
+/// touch and lay out new ones like their neighbors, everything else is written back byte for
+/// byte.
+///
+/// Paths are lists of map keys and array indices, as in `["dependencies", "serde"]` or
+/// `["authors", "0"]`. An empty path stands for the whole document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    /// Everything in front of the document's value, including the root key.
+    prefix: String,
+    value: Node,
+    /// Everything after the document's value, including its comma.
+    suffix: String,
+    root: Root,
+    layout: Layout,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// A string, number, boolean or null as written, and its value.
+    Scalar(String, Value),
+    Map(Container),
+    Array(Container),
+}
+
+/// The items of a map or array. The delimiters are empty for the braceless root map of
+/// [`Root::Entries`].
+#[derive(Debug, Clone, PartialEq)]
+struct Container {
+    open: &'static str,
+    items: Vec<Item>,
+    /// Whitespace and comments between the last item and the closing delimiter.
+    end: String,
+    close: &'static str,
+}
+
+/// A map entry or array element.
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    /// Whitespace and comments in front of the item, from the end of the previous item's line.
+    leading: String,
+    /// The key as written and the key it stands for, for map entries.
+    key: Option<(String, Box<str>)>,
+    /// Whitespace and comments between the key and the value.
+    separator: String,
+    value: Node,
+    /// Whitespace and comments between the value and the comma, if there is a comma.
+    comma: Option<String>,
+    /// Whitespace and comments after the comma, up to and including the end of its line.
+    trailing: String,
+}
+
+/// How new items are laid out, picked up from the input.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    newline: NewlineStyle,
+    /// What each level of nesting is indented by.
+    indent: String,
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::parse_with_options(input, &ParseOptions::default())
+    }
+
+    /// Parses a document laid out as `options` asks for, see [`ParseOptions::root`]. Comments are
+    /// always kept.
+    pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        // The parser reports any errors, the tree is then built knowing the input is valid.
+        parse(tokens.clone(), options.clone())?;
+
+        let mut parser = CstParser::new(input, tokens);
+        let (prefix, value) = match &options.root {
+            Root::Entries => (String::new(), Node::Map(parser.container(Token::Eof))),
+            Root::Bare => (parser.trivia(), parser.value()),
+            Root::Key(_) | Root::AnyKey => {
+                let mut prefix = parser.trivia();
+                prefix.push_str(parser.bump().1);
+                prefix.push_str(&parser.trivia());
+                (prefix, parser.value())
+            }
+        };
+        let suffix = input[parser.offset..].to_owned();
+
+        let layout = Layout {
+            newline: if input.contains("\r\n") {
+                NewlineStyle::CrLf
+            } else {
+                NewlineStyle::Lf
+            },
+            indent: value.indent_unit("").unwrap_or_else(|| "    ".to_owned()),
+        };
+        Ok(Document {
+            prefix,
+            value,
+            suffix,
+            root: options.root.clone(),
+            layout,
+        })
+    }
+
+    /// Returns the document's value, without comments.
+    pub fn value(&self) -> Value {
+        self.value.to_value()
+    }
+
+    /// Returns the value at `path`, without comments. Of duplicate keys the last one counts.
+    pub fn get(&self, path: &[&str]) -> Option<Value> {
+        let mut node = &self.value;
+        for segment in path {
+            let index = node.position(segment)?;
+            node = &node.container()?.items[index].value;
+        }
+        Some(node.to_value())
+    }
+
+    /// Replaces the value at `path`, keeping the comments around it. A missing map entry is
+    /// added after the last entry of its map, everything else on the path has to exist.
+    pub fn set<T>(&mut self, path: &[&str], value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let Some((last, parent)) = path.split_last() else {
+            let root = match self.root {
+                Root::Entries => Root::Entries,
+                _ => Root::Bare,
+            };
+            let value = self.layout.render(value, "", &root)?;
+            if !matches!(value, Node::Map(_)) && self.root != Root::Bare {
+                return Err(Error::Path(
+                    "the document's value has to be a map".to_owned(),
+                ));
+            }
+            self.value = value;
+            return Ok(());
+        };
+
+        let layout = self.layout.clone();
+        let (node, indent) = self.node_mut(parent).ok_or_else(|| missing(parent))?;
+        match node.position(last) {
+            Some(index) => {
+                let container = node.container_mut().expect("found an item");
+                let indent = container.item_indent(index, &indent);
+                container.items[index].value = layout.render(value, &indent, &Root::Bare)?;
+                Ok(())
+            }
+            None => match node {
+                Node::Map(map) => map.insert(map.items.len(), Some(last), value, &indent, &layout),
+                _ => Err(missing(path)),
+            },
+        }
+    }
+
+    /// Adds a map entry or array element at `path`. The map must not have the key yet, the
+    /// element is inserted in front of the element at that index, or after the last one for
+    /// the index `-` or the length of the array.
+    pub fn insert<T>(&mut self, path: &[&str], value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let (last, parent) = path.split_last().ok_or_else(|| missing(path))?;
+        let layout = self.layout.clone();
+        let (node, indent) = self.node_mut(parent).ok_or_else(|| missing(parent))?;
+        match node {
+            Node::Map(map) => {
+                if map.items.iter().any(|item| item.key_is(last)) {
+                    return Err(Error::Path(format!("`{}` already exists", display(path))));
+                }
+                map.insert(map.items.len(), Some(last), value, &indent, &layout)
+            }
+            Node::Array(array) => {
+                let index = match *last {
+                    "-" => array.items.len(),
+                    index => index
+                        .parse()
+                        .ok()
+                        .filter(|&index| index <= array.items.len())
+                        .ok_or_else(|| missing(path))?,
+                };
+                array.insert(index, None, value, &indent, &layout)
+            }
+            Node::Scalar(..) => Err(missing(path)),
+        }
+    }
+
+    /// Removes the map entry or array element at `path` together with its comments, returning
+    /// whether there was one.
+    pub fn remove(&mut self, path: &[&str]) -> bool {
+        let Some((last, parent)) = path.split_last() else {
+            return false;
+        };
+        let Some((node, _)) = self.node_mut(parent) else {
+            return false;
+        };
+        let Some(index) = node.position(last) else {
+            return false;
+        };
+        node.container_mut().expect("found an item").remove(index);
+        true
+    }
+
+    /// Finds the node at `path` together with the indentation of the line it starts on.
+    fn node_mut(&mut self, path: &[&str]) -> Option<(&mut Node, String)> {
+        let mut node = &mut self.value;
+        let mut indent = String::new();
+        for segment in path {
+            let index = node.position(segment)?;
+            let container = node.container_mut()?;
+            indent = container.item_indent(index, &indent);
+            node = &mut container.items[index].value;
+        }
+        Some((node, indent))
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.prefix, self.value, self.suffix)
+    }
+}
+
+impl Node {
+    fn to_value(&self) -> Value {
+        match self {
+            Node::Scalar(_, value) => value.clone(),
+            Node::Map(map) => Value::Map(
+                map.items
+                    .iter()
+                    .map(|item| {
+                        let (_, key) = item.key.as_ref().expect("map items have keys");
+                        (key.clone(), item.value.to_value())
+                    })
+                    .collect(),
+            ),
+            Node::Array(array) => Value::Array(
+                array
+                    .items
+                    .iter()
+                    .map(|item| item.value.to_value())
+                    .collect(),
+            ),
+        }
+    }
+
+    fn container(&self) -> Option<&Container> {
+        match self {
+            Node::Map(container) | Node::Array(container) => Some(container),
+            Node::Scalar(..) => None,
+        }
+    }
+
+    fn container_mut(&mut self) -> Option<&mut Container> {
+        match self {
+            Node::Map(container) | Node::Array(container) => Some(container),
+            Node::Scalar(..) => None,
+        }
+    }
+
+    /// Finds the item `segment` refers to: the last entry with that key in a map, or the element
+    /// at that index in an array.
+    fn position(&self, segment: &str) -> Option<usize> {
+        match self {
+            Node::Map(map) => map.items.iter().rposition(|item| item.key_is(segment)),
+            Node::Array(array) => segment
+                .parse()
+                .ok()
+                .filter(|&index| index < array.items.len()),
+            Node::Scalar(..) => None,
+        }
+    }
+
+    /// Finds how much an item is indented more than its container, where this node starts on a
+    /// line indented by `indent`.
+    fn indent_unit(&self, indent: &str) -> Option<String> {
+        let container = self.container()?;
+        (0..container.items.len()).find_map(|index| {
+            let item_indent = container.item_indent(index, indent);
+            match item_indent.strip_prefix(indent) {
+                Some(unit) if !unit.is_empty() => Some(unit.to_owned()),
+                _ => container.items[index].value.indent_unit(&item_indent),
+            }
+        })
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Scalar(text, _) => f.write_str(text),
+            Node::Map(container) | Node::Array(container) => {
+                f.write_str(container.open)?;
+                for item in &container.items {
+                    write!(f, "{item}")?;
+                }
+                write!(f, "{}{}", container.end, container.close)
+            }
+        }
+    }
+}
+
+impl Container {
+    /// Whether the item at `index` is the first thing on its line.
+    fn starts_line(&self, index: usize) -> bool {
+        self.items[index].leading.contains('\n')
+            || match index.checked_sub(1) {
+                Some(previous) => self.items[previous].trailing.ends_with('\n'),
+                None => self.open.is_empty(),
+            }
+    }
+
+    /// How much of the leading text of the item at `index` ends the line before it. The rest
+    /// belongs to the item.
+    fn previous_line_end(&self, index: usize) -> usize {
+        let line_ended = match index.checked_sub(1) {
+            Some(previous) => self.items[previous].trailing.ends_with('\n'),
+            None => self.open.is_empty(),
+        };
+        match self.items[index].leading.find('\n') {
+            Some(newline) if !line_ended => newline + 1,
+            _ => 0,
+        }
+    }
+
+    /// The indentation of the line the item at `index` starts on, where the container starts on
+    /// a line indented by `indent`.
+    fn item_indent(&self, index: usize, indent: &str) -> String {
+        if !self.starts_line(index) {
+            return indent.to_owned();
+        }
+        let leading = &self.items[index].leading;
+        let line = leading
+            .rfind('\n')
+            .map_or(leading.as_str(), |newline| &leading[newline + 1..]);
+        line.chars()
+            .take_while(|c| matches!(c, ' ' | '\t'))
+            .collect()
+    }
+
+    /// Inserts an item in front of the one at `index`, laid out like the items around it.
+    fn insert<T>(
+        &mut self,
+        index: usize,
+        key: Option<&str>,
+        value: &T,
+        indent: &str,
+        layout: &Layout,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let newline = layout.newline();
+        let on_own_line = match index.checked_sub(1) {
+            _ if self.items.is_empty() => true,
+            _ if index < self.items.len() => self.starts_line(index),
+            Some(last) => {
+                self.starts_line(last)
+                    || self.items[last].trailing.ends_with('\n')
+                    || self.end.contains('\n')
+            }
+            None => unreachable!("index 0 of an empty container is handled above"),
+        };
+        let item_indent = if self.items.is_empty() {
+            if self.open.is_empty() {
+                String::new()
+            } else {
+                format!("{indent}{}", layout.indent)
+            }
+        } else if on_own_line {
+            self.item_indent(index.min(self.items.len() - 1), indent)
+        } else {
+            indent.to_owned()
+        };
+        let value = layout.render(value, &item_indent, &Root::Bare)?;
+
+        let mut item = Item {
+            leading: String::new(),
+            key: key.map(|key| (key_literal(key, false).into_owned(), key.into())),
+            separator: " ".to_owned(),
+            value,
+            comma: Some(String::new()),
+            trailing: String::new(),
+        };
+        if self.items.is_empty() {
+            let comments = self.end.trim_end();
+            if !comments.is_empty() || !self.open.is_empty() {
+                item.leading = format!("{comments}{newline}{item_indent}");
+            }
+            item.trailing = newline.to_owned();
+            self.end = if self.open.is_empty() {
+                String::new()
+            } else {
+                indent.to_owned()
+            };
+        } else if index == self.items.len() {
+            let last = self.items.last_mut().expect("not empty");
+            if last.comma.is_none() {
+                last.comma = Some(String::new());
+                item.comma = None;
+            }
+            if !on_own_line {
+                item.leading = " ".to_owned();
+            } else if last.trailing.ends_with('\n') {
+                item.leading = item_indent;
+                item.trailing = newline.to_owned();
+            } else {
+                item.leading = format!("{newline}{item_indent}");
+            }
+        } else {
+            let next = &mut self.items[index];
+            if !on_own_line {
+                item.leading = std::mem::replace(&mut next.leading, " ".to_owned());
+            } else {
+                // Comments in front of the next item stay with it.
+                let line_end = self.previous_line_end(index);
+                let next = &mut self.items[index];
+                item.leading = format!("{}{item_indent}", &next.leading[..line_end]);
+                next.leading.drain(..line_end);
+                item.trailing = newline.to_owned();
+            }
+        }
+        self.items.insert(index, item);
+        Ok(())
+    }
+
+    /// Removes the item at `index` along with its comments and the lines it leaves empty.
+    fn remove(&mut self, index: usize) {
+        let starts_line = self.starts_line(index);
+        let indent = self.item_indent(index, "");
+        let line_end = self.previous_line_end(index);
+        let removed = self.items.remove(index);
+        let last = index == self.items.len();
+        if last && removed.comma.is_none() && index > 0 {
+            self.items[index - 1].comma = None;
+        }
+
+        let rest = if last {
+            &mut self.end
+        } else {
+            &mut self.items[index].leading
+        };
+        let head = &removed.leading[..line_end];
+        let after_spaces = rest.trim_start_matches([' ', '\t']);
+        let line_ends_in_rest = after_spaces.starts_with(['\n', '\r']);
+
+        *rest = if starts_line {
+            if !removed.trailing.is_empty() {
+                format!("{head}{rest}")
+            } else if line_ends_in_rest {
+                let newline = after_spaces.find('\n').expect("starts with a line break");
+                format!("{head}{}", &after_spaces[newline + 1..])
+            } else {
+                format!("{head}{indent}{after_spaces}")
+            }
+        } else if !removed.trailing.is_empty() {
+            let newline = if removed.trailing.ends_with("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            };
+            format!("{newline}{rest}")
+        } else if last {
+            rest.clone()
+        } else {
+            format!("{}{after_spaces}", removed.leading)
+        };
+    }
+}
+
+impl Item {
+    fn key_is(&self, key: &str) -> bool {
+        self.key.as_ref().is_some_and(|(_, k)| **k == *key)
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.leading)?;
+        if let Some((key, _)) = &self.key {
+            write!(f, "{key}{}", self.separator)?;
+        }
+        write!(f, "{}", self.value)?;
+        if let Some(before) = &self.comma {
+            write!(f, "{before},")?;
+        }
+        f.write_str(&self.trailing)
+    }
+}
+
+impl Layout {
+    fn newline(&self) -> &'static str {
+        match self.newline {
+            NewlineStyle::Lf => "\n",
+            NewlineStyle::CrLf => "\r\n",
+        }
+    }
+
+    /// Serializes `value` as it would be written on a line indented by `indent`.
+    fn render<T>(&self, value: &T, indent: &str, root: &Root) -> Result<Node>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut config = PrettyConfig::new().root(root.clone()).newline(self.newline);
+        if self.indent.chars().all(|c| c == '\t') {
+            config = config.tabs(true);
+        } else {
+            config = config.indent_width(self.indent.chars().count());
+        }
+
+        let newline = self.newline();
+        let text = to_string_pretty(value, config)?.replace(newline, &format!("{newline}{indent}"));
+        let mut parser = CstParser::new(&text, tokenize(&text)?);
+        Ok(match root {
+            Root::Entries => Node::Map(parser.container(Token::Eof)),
+            _ => {
+                parser.trivia();
+                parser.value()
+            }
+        })
+    }
+}
+
+/// Builds the tree from tokens that are known to parse.
+struct CstParser<'a> {
+    input: &'a str,
+    tokens: Vec<SpannedToken<'a>>,
+    pos: usize,
+    /// Where the text not yet in the tree starts.
+    offset: usize,
+}
+
+impl<'a> CstParser<'a> {
+    fn new(input: &'a str, tokens: Vec<SpannedToken<'a>>) -> Self {
+        CstParser {
+            input,
+            tokens,
+            pos: 0,
+            offset: 0,
+        }
+    }
+
+    /// Skips to the next token that isn't a comment.
+    fn peek(&mut self) -> &Token<'a> {
+        while matches!(self.tokens[self.pos].token, Token::Comment(..)) {
+            self.pos += 1;
+        }
+        &self.tokens[self.pos].token
+    }
+
+    /// Takes the whitespace and comments in front of the next token.
+    fn trivia(&mut self) -> String {
+        self.peek();
+        let start = self.tokens[self.pos].span.start;
+        let trivia = self.input[self.offset..start].to_owned();
+        self.offset = start;
+        trivia
+    }
+
+    /// Takes the whitespace and comments up to and including the end of the current line, if it
+    /// ends before the next token.
+    fn line_end(&mut self) -> String {
+        let first = self.pos;
+        self.peek();
+        let mut gap_start = self.offset;
+        let mut gaps = Vec::new();
+        for comment in &self.tokens[first..self.pos] {
+            gaps.push(gap_start..comment.span.start);
+            gap_start = comment.span.end;
+        }
+        gaps.push(gap_start..self.tokens[self.pos].span.start);
+
+        for gap in gaps {
+            if let Some(newline) = self.input[gap.clone()].find('\n') {
+                let end = gap.start + newline + 1;
+                let trivia = self.input[self.offset..end].to_owned();
+                self.offset = end;
+                return trivia;
+            }
+        }
+        String::new()
+    }
+
+    /// Takes the next token, whose trivia has to be taken already, and its text.
+    fn bump(&mut self) -> (Token<'a>, &'a str) {
+        self.peek();
+        let tok = self.tokens[self.pos].clone();
+        self.pos += 1;
+        self.offset = tok.span.end;
+        (tok.token, &self.input[tok.span.start..tok.span.end])
+    }
+
+    fn value(&mut self) -> Node {
+        let (token, text) = self.bump();
+        match token {
+            Token::OpenCurly => Node::Map(Container {
+                open: "{",
+                close: "}",
+                ..self.container(Token::CloseCurly)
+            }),
+            Token::OpenBracket => Node::Array(Container {
+                open: "[",
+                close: "]",
+                ..self.container(Token::CloseBracket)
+            }),
+            Token::String(s) => Node::Scalar(text.to_owned(), Value::String(s.into_owned())),
+            Token::Bytes(b) => Node::Scalar(text.to_owned(), Value::Bytes(b.into_owned())),
+            Token::Number(num) => {
+                Node::Scalar(text.to_owned(), Value::Number(Number::parse(&num).unwrap()))
+            }
+            Token::True => Node::Scalar(text.to_owned(), Value::Bool(true)),
+            Token::False => Node::Scalar(text.to_owned(), Value::Bool(false)),
+            Token::Null => Node::Scalar(text.to_owned(), Value::Null),
+            token => unreachable!("the parser accepted `{token}` as a value"),
+        }
+    }
+
+    /// Takes the items up to and including `close`, after the opening delimiter. The
+    /// delimiters are left empty for the caller to fill in.
+    fn container(&mut self, close: Token<'static>) -> Container {
+        let is_map = close != Token::CloseBracket;
+        let mut items = Vec::new();
+        let end = loop {
+            let leading = self.trivia();
+            if *self.peek() == close {
+                break leading;
+            }
+
+            let (key, separator) = if is_map {
+                let (token, text) = self.bump();
+                let (Token::Key(key) | Token::String(key)) = token else {
+                    unreachable!("the parser accepted `{token}` as a key");
+                };
+                (Some((text.to_owned(), key.into())), self.trivia())
+            } else {
+                (None, String::new())
+            };
+            let value = self.value();
+            let before_comma = self.trivia();
+            let mut item = Item {
+                leading,
+                key,
+                separator,
+                value,
+                comma: None,
+                trailing: String::new(),
+            };
+
+            if *self.peek() != Token::Comma {
+                items.push(item);
+                break before_comma;
+            }
+            self.bump();
+            item.comma = Some(before_comma);
+            item.trailing = self.line_end();
+            items.push(item);
+        };
+        self.bump();
+
+        Container {
+            open: "",
+            items,
+            end,
+            close: "",
+        }
+    }
+}
+
+fn missing(path: &[&str]) -> Error {
+    Error::Path(format!("no value at `{}`", display(path)))
+}
+
+fn display(path: &[&str]) -> String {
+    path.iter().map(|segment| format!("/{segment}")).collect()
+}
@@ -1,49 +1,1387 @@
-use crate::ast::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::ast::{self, Number, Value, map};
 use crate::error::{Error, Result};
-use crate::lexer::tokenize;
-use crate::parser::parse;
-use serde::Deserializer;
-use serde::de::{self, DeserializeOwned, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use crate::lexer::{Lexer, Span, SpannedToken, Token, tokenize, tokenize_recovering};
+use crate::parser::{
+    Diagnostic, DuplicateKeys, Expected, ParseError, ParseOptions, Root, container, parse,
+    parse_recovering,
+};
+use serde::de::{
+    self, Deserialize, DeserializeOwned, Deserializer as _, IgnoredAny, IntoDeserializer,
+    MapAccess, SeqAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+/// Deserializes a `root { ... },` document. Anything but whitespace and comments after it is an
+/// error, use [`Deserializer::into_iter`] to read several documents from one input.
+///
+/// The input is read directly without building an intermediate [`Value`], and strings without
+/// escape sequences are borrowed from it, so `&str` and `#[serde(borrow)]` fields are supported.
+pub fn from_str<'a, T>(s: &'a str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_str_with_options(s, &ParseOptions::default())
+}
+
+/// Deserializes a document laid out as `options` asks for, see [`ParseOptions::root`], and with
+/// duplicate keys handled as [`ParseOptions::duplicate_keys`] says. Comments are always skipped.
+pub fn from_str_with_options<'a, T>(s: &'a str, options: &ParseOptions) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    let mut deserializer = Deserializer::with_options(s, options);
+    let value = deserializer.document()?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Iterator over the documents in one input, created by [`Deserializer::into_iter`].
+///
+/// Iteration stops after the first error, since there is no telling where the next document
+/// starts.
+pub struct StreamDeserializer<'de, T> {
+    de: Deserializer<'de>,
+    failed: bool,
+    marker: PhantomData<T>,
+}
+
+impl<'de, T> Iterator for StreamDeserializer<'de, T>
+where
+    T: Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.failed {
+            return None;
+        }
+        let result = match self.de.peek() {
+            Ok(Token::Eof) => return None,
+            Ok(_) => self.de.document(),
+            Err(err) => Err(err),
+        };
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+/// Iterator over the documents read from an [`io::Read`], like [`StreamDeserializer`].
+///
+/// The reader is read a chunk at a time as the iterator advances, and each document is returned
+/// as soon as it has been read in full, so documents appended to a pipe or a growing file come
+/// out one by one. Only the document being read is kept in memory. A document counts as read
+/// once its trailing `,` is, or the reader has ended.
+pub struct ReaderStreamDeserializer<R, T> {
+    reader: R,
+    options: ParseOptions,
+    /// Text read but not yet deserialized, starting at or before the next document.
+    buffer: String,
+    /// Bytes at the end of what was read that don't make up a whole character yet.
+    partial: Vec<u8>,
+    /// How many bytes were dropped from the front of `buffer`, to report errors at their
+    /// position in the whole input.
+    consumed: usize,
+    /// Where the next document starts in `buffer`.
+    position: Span,
+    /// Set once the reader has no more to give.
+    eof: bool,
+    failed: bool,
+    marker: PhantomData<T>,
+}
+
+impl<R, T> ReaderStreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    /// Creates a stream over the documents `reader` holds, which have to be valid UTF-8. Nothing
+    /// is read until the first document is asked for.
+    pub fn new(reader: R, options: &ParseOptions) -> Self {
+        ReaderStreamDeserializer {
+            reader,
+            options: options.clone(),
+            buffer: String::new(),
+            partial: Vec::new(),
+            consumed: 0,
+            position: Lexer::new("").position(),
+            eof: false,
+            failed: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Reads the next chunk into the buffer. Chunks grow with the document being read, so a
+    /// large one isn't lexed again for every few bytes.
+    fn fill(&mut self) -> Result<()> {
+        let mut chunk = vec![0; (self.buffer.len() - self.position.start).max(8192)];
+        let read = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::Io(e.to_string())),
+            }
+        };
+        if read == 0 {
+            self.eof = true;
+            if !self.partial.is_empty() {
+                return Err(invalid_utf8());
+            }
+            return Ok(());
+        }
+
+        self.partial.extend_from_slice(&chunk[..read]);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            // Only a character cut off at the end of the chunk may be completed by the next.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        };
+        let text = std::str::from_utf8(&self.partial[..valid]).expect("checked above");
+        self.buffer.push_str(text);
+        self.partial.drain(..valid);
+        Ok(())
+    }
+}
+
+fn invalid_utf8() -> Error {
+    Error::Io("stream did not contain valid UTF-8".to_owned())
+}
+
+impl<R, T> Iterator for ReaderStreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if self.failed {
+                return None;
+            }
+            let lexer = Lexer::resume(&self.buffer, self.position);
+            let mut de = Deserializer::from_lexer(lexer, &self.options);
+            let result = match de.peek() {
+                Ok(Token::Eof) => None,
+                Ok(_) => Some(de.document()),
+                Err(err) => Some(Err(err)),
+            };
+            // What was read may end in the middle of a document, or before the comma after it.
+            let cut_off = de.at_eof()
+                || matches!(&result, Some(Err(err))
+                    if err.span().is_some_and(|span| span.end >= self.buffer.len()));
+            if cut_off && !self.eof {
+                if let Err(err) = self.fill() {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+                continue;
+            }
+
+            let position = de.position();
+            let result = result?.map_err(|err| err.offset(self.consumed));
+            self.failed = result.is_err();
+            // Drop the text of the documents already read.
+            self.buffer.drain(..position.start);
+            self.consumed += position.start;
+            self.position = Span {
+                start: 0,
+                end: 0,
+                ..position
+            };
+            return Some(result);
+        }
+    }
+}
+
+/// Parses `s` straight into a [`Value`] without going through serde, so everything the parser
+/// records, such as preserved comments, is kept.
+pub fn parse_value(s: &str, options: &ParseOptions) -> Result<Value> {
+    let tokens = tokenize(s)?;
+    Ok(parse(tokens, options.clone())?)
+}
+
+/// Parses `s` like [`parse_value`], but carries on past errors to report all of them at once,
+/// sorted by position. Parts of the input that failed to parse are left as [`Value::Invalid`]
+/// in the returned value, which is only meaningful when none of the diagnostics are errors.
+///
+/// Keys that [`DuplicateKeys::FirstWins`] or [`DuplicateKeys::LastWins`] drop a value of are
+/// reported as warnings.
+pub fn parse_value_recovering(s: &str, options: &ParseOptions) -> (Value, Vec<Diagnostic>) {
+    let (tokens, lex_errors) = tokenize_recovering(s);
+    let (value, mut diagnostics) = parse_recovering(tokens, options.clone());
+    diagnostics.extend(lex_errors.into_iter().map(Diagnostic::from));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    (value, diagnostics)
+}
+
+/// A glass deserializer reading tokens straight from the input.
+pub struct Deserializer<'de> {
+    lexer: Lexer<'de>,
+    peeked: Option<SpannedToken<'de>>,
+    /// Closing delimiters of the maps and arrays being read together with the spans of their
+    /// opening delimiters, innermost last.
+    open: Vec<(Token<'static>, Span)>,
+    /// Set until the root map is read when its entries are at the top level without braces.
+    root_entries: bool,
+    root: Root,
+    duplicate_keys: DuplicateKeys,
+    /// The repeated keys of the maps in the document being read, shared with the deserializers
+    /// of [`DuplicateKeys::Collect`]ed values.
+    duplicates: Arc<DuplicateScan<'de>>,
+}
+
+impl<'de> Deserializer<'de> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Self {
+        Self::with_options(input, &ParseOptions::default())
+    }
+
+    /// Creates a deserializer for documents laid out as `options` asks for, see
+    /// [`ParseOptions::root`], and with duplicate keys handled as
+    /// [`ParseOptions::duplicate_keys`] says. Comments are always skipped.
+    ///
+    /// With [`DuplicateKeys::LastWins`] and [`DuplicateKeys::Collect`] each map's keys are read
+    /// ahead to find the duplicates.
+    pub fn with_options(input: &'de str, options: &ParseOptions) -> Self {
+        Self::from_lexer(Lexer::new(input), options)
+    }
+
+    fn from_lexer(lexer: Lexer<'de>, options: &ParseOptions) -> Self {
+        Deserializer {
+            lexer,
+            peeked: None,
+            open: Vec::new(),
+            root_entries: false,
+            root: options.root.clone(),
+            duplicate_keys: options.duplicate_keys,
+            duplicates: Arc::default(),
+        }
+    }
+
+    /// Turns the deserializer into an iterator over the documents in its input, such as a log
+    /// of `root { ... },` records. Every document but the last has to end with a comma.
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T>(self) -> StreamDeserializer<'de, T>
+    where
+        T: Deserialize<'de>,
+    {
+        StreamDeserializer {
+            de: self,
+            failed: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Reads the next document, including the comma after it.
+    fn document<T>(&mut self) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        self.document_seed(PhantomData)
+    }
+
+    pub(crate) fn document_seed<T>(&mut self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.parse_root()?;
+        let value = seed.deserialize(&mut *self)?;
+        self.end_entry(Token::Eof)?;
+        Ok(value)
+    }
+
+    /// Checks that nothing follows the document.
+    pub(crate) fn end(&mut self) -> Result<()> {
+        if *self.peek()? == Token::Eof {
+            return Ok(());
+        }
+        let after = match self.root {
+            Root::Bare => "root value",
+            _ => "root map",
+        };
+        let tok = self.next()?;
+        Err(self.unexpected(tok, &["end of file"], Some(after)))
+    }
+
+    /// Whether the end of the input has been looked at, in which case a document that ends there
+    /// may be followed by more of it when the input is still being read.
+    fn at_eof(&self) -> bool {
+        matches!(&self.peeked, Some(tok) if tok.token == Token::Eof)
+    }
+
+    /// Where the next document starts, after the last one read.
+    fn position(&self) -> Span {
+        match &self.peeked {
+            Some(tok) => tok.span,
+            None => self.lexer.position(),
+        }
+    }
+
+    /// Consumes the top-level key if the root mode has one, leaving the deserializer at the root
+    /// value.
+    fn parse_root(&mut self) -> Result<()> {
+        let root = self.root.clone();
+        match &root {
+            Root::Entries => {
+                self.root_entries = true;
+                return Ok(());
+            }
+            Root::Bare => return Ok(()),
+            Root::Key(_) | Root::AnyKey => {}
+        }
+
+        let tok = self.next()?;
+        match &tok.token {
+            Token::Key(key) | Token::String(key) => {
+                if let Root::Key(name) = &root
+                    && key != name
+                {
+                    return Err(ParseError::InvalidRoot(root.expected_key(), tok.span).into());
+                }
+            }
+            _ => {
+                let expected = Expected {
+                    one_of: vec![root.expected_key()],
+                    ..Expected::default()
+                };
+                return Err(expected.found(tok).into());
+            }
+        }
+
+        if *self.peek()? != Token::OpenCurly {
+            let tok = self.next()?;
+            return Err(self.unexpected(tok, &["`{`"], Some("root key")));
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<&Token<'de>> {
+        let tok = match self.peeked.take() {
+            Some(tok) => tok,
+            None => self.lex()?,
+        };
+        Ok(&self.peeked.insert(tok).token)
+    }
+
+    fn next(&mut self) -> Result<SpannedToken<'de>> {
+        match self.peeked.take() {
+            Some(tok) => Ok(tok),
+            None => self.lex(),
+        }
+    }
+
+    /// Lexes the next token that isn't a comment.
+    fn lex(&mut self) -> Result<SpannedToken<'de>> {
+        loop {
+            let tok = self.lexer.next_token()?;
+            if !matches!(tok.token, Token::Comment(..)) {
+                return Ok(tok);
+            }
+        }
+    }
+
+    /// Reads a number, leaving it to the visitor to reject floats or out of range integers for
+    /// the type it wants.
+    fn deserialize_number<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        match &tok.token {
+            Token::Number(num) => Number::parse(num).unwrap().visit(visitor),
+            _ => Err(self.invalid_type(tok, "number")),
+        }
+    }
+
+    /// Returns the keys that appear more than once in the map being opened, with where each of
+    /// their values starts. `close` is the token that ends the map.
+    ///
+    /// The first map opened outside the text scanned so far has its whole subtree scanned, so
+    /// the maps inside it are only looked up.
+    fn map_duplicates(&mut self, close: &Token) -> HashMap<Cow<'de, str>, Duplicate> {
+        let open = match close {
+            Token::Eof => None,
+            _ => self.open.last().map(|(_, span)| span.start),
+        };
+        if !open.is_some_and(|open| self.duplicates.range.contains(&open)) {
+            self.duplicates = Arc::new(self.scan_duplicates(open));
+        }
+        let Some(keys) = self.duplicates.maps.get(&open.unwrap_or(usize::MAX)) else {
+            return HashMap::new();
+        };
+        keys.iter()
+            .map(|(key, values)| {
+                let values = values.clone();
+                (key.clone(), Duplicate { values, read: 0 })
+            })
+            .collect()
+    }
+
+    /// Reads ahead over the map being opened, whose `{` starts at `open`, and everything inside
+    /// it, and records the keys of each map that appear more than once. Stops at the end of the
+    /// map or at a lexer error, leaving errors to be reported when the map is read for real.
+    fn scan_duplicates(&self, open: Option<usize>) -> DuplicateScan<'de> {
+        let mut peeked = self.peeked.clone();
+        let mut lexer = self.lexer.clone();
+        let mut next = || loop {
+            let tok = match peeked.take() {
+                Some(tok) => tok,
+                None => lexer.next_token().ok()?,
+            };
+            if !matches!(tok.token, Token::Comment(..)) {
+                return Some(tok);
+            }
+        };
+
+        let start = open.unwrap_or_else(|| self.position().start);
+        let mut scan = DuplicateScan {
+            range: start..self.lexer.input().len(),
+            maps: HashMap::new(),
+        };
+        let mut frames = vec![ScanFrame::new(open.unwrap_or(usize::MAX), true)];
+        while let Some(tok) = next() {
+            let frame = frames.last_mut().expect("the scanned map is open");
+            match tok.token {
+                Token::CloseCurly | Token::CloseBracket => {
+                    scan.record(frames.pop().expect("the scanned map is open"));
+                    match frames.last_mut() {
+                        Some(parent) => parent.entry = ScanEntry::Comma,
+                        None => {
+                            scan.range.end = tok.span.end;
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                Token::Eof => break,
+                Token::Comma => {
+                    frame.entry = ScanEntry::Key;
+                    continue;
+                }
+                _ => {}
+            }
+            if frame.map {
+                match std::mem::replace(&mut frame.entry, ScanEntry::Comma) {
+                    ScanEntry::Key => {
+                        if let Token::Key(key) | Token::String(key) = tok.token {
+                            frame.entry = ScanEntry::Value(key);
+                        }
+                        continue;
+                    }
+                    ScanEntry::Value(key) => frame.values.entry(key).or_default().push(tok.span),
+                    ScanEntry::Comma => {}
+                }
+            }
+            match tok.token {
+                Token::OpenCurly => frames.push(ScanFrame::new(tok.span.start, true)),
+                Token::OpenBracket => frames.push(ScanFrame::new(tok.span.start, false)),
+                _ => {}
+            }
+        }
+        for frame in frames {
+            scan.record(frame);
+        }
+        scan
+    }
+
+    /// Consumes the comma after an entry, which may be left out before the `close` token.
+    fn end_entry(&mut self, close: Token<'static>) -> Result<()> {
+        if *self.peek()? == close {
+            return Ok(());
+        }
+
+        let (one_of, after): (&[&'static str], _) = match close {
+            Token::CloseCurly => (&["`,`", "`}`"], "map value"),
+            Token::CloseBracket => (&["`,`", "`]`"], "array element"),
+            _ => (&["`,`", "end of file"], "root map"),
+        };
+        self.expect(Token::Comma, one_of, Some(after))
+    }
+
+    fn expect(
+        &mut self,
+        token: Token<'static>,
+        one_of: &[&'static str],
+        after: Option<&'static str>,
+    ) -> Result<()> {
+        let tok = self.next()?;
+        if tok.token == token {
+            Ok(())
+        } else {
+            Err(self.unexpected(tok, one_of, after))
+        }
+    }
+
+    /// Consumes the closing delimiter of the innermost map or array.
+    fn close(&mut self) -> Result<()> {
+        let (closer, _) = self.open.last().cloned().expect("a map or array is open");
+        let one_of: &[&'static str] = match closer {
+            Token::CloseCurly => &["`}`"],
+            _ => &["`]`"],
+        };
+        self.expect(closer, one_of, None)?;
+        self.open.pop();
+        Ok(())
+    }
+
+    /// Builds the error for finding `tok` instead of one of `one_of`.
+    fn unexpected(
+        &self,
+        tok: SpannedToken<'_>,
+        one_of: &[&'static str],
+        after: Option<&'static str>,
+    ) -> Error {
+        let expected = Expected {
+            within: self
+                .open
+                .last()
+                .map(|(closer, start)| (container(closer), *start)),
+            ..Expected::new(one_of, after)
+        };
+        expected.found(tok).into()
+    }
+
+    fn invalid_type(&self, tok: SpannedToken<'_>, expected: &str) -> Error {
+        match tok.token {
+            Token::Eof | Token::CloseCurly | Token::CloseBracket | Token::Comma | Token::Key(_) => {
+                self.unexpected(tok, &["a value"], None)
+            }
+            token => Error::Serde(format!(
+                "expected {}, got `{}` at {}",
+                expected, token, tok.span
+            )),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.root_entries {
+            return self.deserialize_map(visitor);
+        }
+        match self.peek()? {
+            Token::OpenCurly => self.deserialize_map(visitor),
+            Token::OpenBracket => self.deserialize_seq(visitor),
+            Token::String(_) => self.deserialize_str(visitor),
+            Token::Bytes(_) => self.deserialize_bytes(visitor),
+            Token::Number(_) => self.deserialize_number(visitor),
+            Token::True | Token::False => self.deserialize_bool(visitor),
+            Token::Null => self.deserialize_unit(visitor),
+            _ => {
+                let tok = self.next()?;
+                Err(self.unexpected(tok, &["a value"], None))
+            }
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        match tok.token {
+            Token::True => visitor.visit_bool(true),
+            Token::False => visitor.visit_bool(false),
+            _ => Err(self.invalid_type(tok, "bool")),
+        }
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        match &tok.token {
+            Token::String(s) => {
+                let mut chars = s.chars();
+                if let Some(c) = chars.next()
+                    && chars.next().is_none()
+                {
+                    return visitor.visit_char(c);
+                }
+
+                Err(Error::Serde(format!("expected single char, got {}", s)))
+            }
+            _ => Err(self.invalid_type(tok, "string")),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        match tok.token {
+            Token::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Token::String(Cow::Owned(s)) => visitor.visit_string(s),
+            _ => Err(self.invalid_type(tok, "string")),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    /// Reads a byte string, or the UTF-8 bytes of a string.
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        match tok.token {
+            Token::Bytes(Cow::Borrowed(b)) => visitor.visit_borrowed_bytes(b),
+            Token::Bytes(Cow::Owned(b)) => visitor.visit_byte_buf(b),
+            Token::String(Cow::Borrowed(s)) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            Token::String(Cow::Owned(s)) => visitor.visit_byte_buf(s.into_bytes()),
+            _ => Err(self.invalid_type(tok, "byte string")),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if *self.peek()? == Token::Null {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        match tok.token {
+            Token::Null => visitor.visit_unit(),
+            _ => Err(self.invalid_type(tok, "null")),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tok = self.next()?;
+        if tok.token != Token::OpenBracket {
+            return Err(self.invalid_type(tok, "array"));
+        }
+        self.open.push((Token::CloseBracket, tok.span));
+        let value = visitor.visit_seq(Seq { de: self })?;
+        self.close()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if std::mem::take(&mut self.root_entries) {
+            return visitor.visit_map(Map::new(self, Token::Eof));
+        }
+
+        let tok = self.next()?;
+        if tok.token != Token::OpenCurly {
+            return Err(self.invalid_type(tok, "map"));
+        }
+        self.open.push((Token::CloseCurly, tok.span));
+        let value = visitor.visit_map(Map::new(self, Token::CloseCurly))?;
+        self.close()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            Token::OpenCurly => {
+                let tok = self.next()?;
+                self.open.push((Token::CloseCurly, tok.span));
+                let value = visitor.visit_enum(Enum { de: self })?;
+                self.close()?;
+                Ok(value)
+            }
+            Token::String(_) => {
+                let tok = self.next()?;
+                let Token::String(variant) = tok.token else {
+                    unreachable!("peeked a string");
+                };
+                visitor.visit_enum(MapKey { key: variant })
+            }
+            _ => {
+                let tok = self.next()?;
+                Err(self.invalid_type(tok, "map or string for enum"))
+            }
+        }
+    }
 
-pub fn from_str<T>(s: &str) -> Result<T>
-where
-    T: DeserializeOwned,
-{
-    let tokens = tokenize(s.to_owned())?;
-    let value = parse(tokens)?;
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+}
+
+struct Seq<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> SeqAccess<'de> for Seq<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if *self.de.peek()? == Token::CloseBracket {
+            return Ok(None);
+        }
+        let value = seed.deserialize(&mut *self.de)?;
+        self.de.end_entry(Token::CloseBracket)?;
+        Ok(Some(value))
+    }
+}
+
+struct Map<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    /// `}`, or the end of the input for the entries of a braceless root map.
+    close: Token<'static>,
+    /// The keys read so far and their spans, for [`DuplicateKeys::Error`] and
+    /// [`DuplicateKeys::FirstWins`].
+    keys: HashMap<Cow<'de, str>, Span>,
+    /// The keys that appear more than once, for [`DuplicateKeys::LastWins`] and
+    /// [`DuplicateKeys::Collect`].
+    duplicates: HashMap<Cow<'de, str>, Duplicate>,
+    /// Where the later values of the key just read start, for [`DuplicateKeys::Collect`].
+    collect: Vec<Span>,
+}
+
+/// The keys that appear more than once in the maps of a stretch of the input, found in one pass
+/// over it for [`DuplicateKeys::LastWins`] and [`DuplicateKeys::Collect`].
+#[derive(Default)]
+struct DuplicateScan<'de> {
+    /// The stretch of the input scanned.
+    range: Range<usize>,
+    /// Where each value of the repeated keys starts, by where the map's `{` starts, or
+    /// `usize::MAX` for a braceless root map. Maps without repeated keys are left out.
+    maps: HashMap<usize, HashMap<Cow<'de, str>, Vec<Span>>>,
+}
+
+impl<'de> DuplicateScan<'de> {
+    fn record(&mut self, frame: ScanFrame<'de>) {
+        let repeated: HashMap<_, _> = frame
+            .values
+            .into_iter()
+            .filter(|(_, values)| values.len() > 1)
+            .collect();
+        if frame.map && !repeated.is_empty() {
+            self.maps.insert(frame.open, repeated);
+        }
+    }
+}
+
+/// A map or array open while scanning for duplicate keys.
+struct ScanFrame<'de> {
+    open: usize,
+    map: bool,
+    entry: ScanEntry<'de>,
+    values: HashMap<Cow<'de, str>, Vec<Span>>,
+}
+
+impl ScanFrame<'_> {
+    fn new(open: usize, map: bool) -> Self {
+        ScanFrame {
+            open,
+            map,
+            entry: ScanEntry::Key,
+            values: HashMap::new(),
+        }
+    }
+}
+
+/// What comes next in the entry of a map being scanned.
+enum ScanEntry<'de> {
+    Key,
+    /// The value of this key.
+    Value(Cow<'de, str>),
+    Comma,
+}
+
+struct Duplicate {
+    /// Where each of the key's values starts.
+    values: Vec<Span>,
+    /// How many of them have been read.
+    read: usize,
+}
+
+impl<'a, 'de> Map<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>, close: Token<'static>) -> Self {
+        let duplicates = match de.duplicate_keys {
+            DuplicateKeys::LastWins | DuplicateKeys::Collect => de.map_duplicates(&close),
+            DuplicateKeys::Error | DuplicateKeys::FirstWins => HashMap::new(),
+        };
+        Map {
+            de,
+            close,
+            keys: HashMap::new(),
+            duplicates,
+            collect: Vec::new(),
+        }
+    }
+
+    /// Reads the next key whose value is to be deserialized, skipping the entries the
+    /// [`DuplicateKeys`] option drops.
+    fn next_key(&mut self) -> Result<Option<MapKey<'de>>> {
+        loop {
+            if *self.de.peek()? == self.close {
+                return Ok(None);
+            }
+            let tok = self.de.next()?;
+            let key = match tok.token {
+                Token::Key(key) | Token::String(key) => key,
+                _ if self.close == Token::Eof => {
+                    return Err(self.de.unexpected(tok, &["a key", "end of file"], None));
+                }
+                _ => return Err(self.de.unexpected(tok, &["a key", "`}`"], None)),
+            };
+
+            let keep = match self.de.duplicate_keys {
+                DuplicateKeys::Error => {
+                    if let Some(first) = self.keys.get(&key) {
+                        let key = key.into_owned().into();
+                        return Err(ParseError::DuplicateKey(key, tok.span, *first).into());
+                    }
+                    self.keys.insert(key.clone(), tok.span);
+                    true
+                }
+                DuplicateKeys::FirstWins => self.keys.insert(key.clone(), tok.span).is_none(),
+                DuplicateKeys::LastWins => match self.duplicates.get_mut(&key) {
+                    Some(duplicate) => {
+                        duplicate.read += 1;
+                        duplicate.read == duplicate.values.len()
+                    }
+                    None => true,
+                },
+                DuplicateKeys::Collect => match self.duplicates.get_mut(&key) {
+                    Some(duplicate) => {
+                        duplicate.read += 1;
+                        if duplicate.read == 1 {
+                            self.collect = duplicate.values[1..].to_vec();
+                        }
+                        duplicate.read == 1
+                    }
+                    None => true,
+                },
+            };
+            if keep {
+                return Ok(Some(MapKey { key }));
+            }
+            IgnoredAny::deserialize(&mut *self.de)?;
+            self.end_value()?;
+        }
+    }
+
+    /// Consumes the comma after a value.
+    fn end_value(&mut self) -> Result<()> {
+        if self.close == Token::Eof {
+            if *self.de.peek()? != Token::Eof {
+                self.de
+                    .expect(Token::Comma, &["`,`", "end of file"], Some("map value"))?;
+            }
+            Ok(())
+        } else {
+            self.de.end_entry(Token::CloseCurly)
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Map<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.next_key()? {
+            Some(key) => seed.deserialize(key).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = if self.collect.is_empty() {
+            seed.deserialize(&mut *self.de)?
+        } else {
+            let later = std::mem::take(&mut self.collect);
+            seed.deserialize(Collected {
+                de: &mut *self.de,
+                later,
+            })?
+        };
+        self.end_value()?;
+        Ok(value)
+    }
+}
+
+/// The values of a key that appears more than once, deserialized as an array. The first value
+/// is read where the deserializer is, the later ones by deserializers of their own.
+struct Collected<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    later: Vec<Span>,
+}
 
-    let inner = unwrap_root(value)?;
-    T::deserialize(inner)
+impl<'de> de::Deserializer<'de> for Collected<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut seq = CollectedSeq {
+            de: self.de,
+            first: true,
+            later: self.later.into_iter(),
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+        // The main deserializer has to move past the first value even if the visitor didn't
+        // want it.
+        if seq.first {
+            IgnoredAny::deserialize(&mut *seq.de)?;
+        }
+        Ok(value)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct CollectedSeq<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    /// Whether the first value is still to be read.
+    first: bool,
+    later: std::vec::IntoIter<Span>,
+}
+
+impl<'de> SeqAccess<'de> for &mut CollectedSeq<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if std::mem::take(&mut self.first) {
+            return seed.deserialize(&mut *self.de).map(Some);
+        }
+        let Some(start) = self.later.next() else {
+            return Ok(None);
+        };
+        let options = ParseOptions::default()
+            .root(Root::Bare)
+            .duplicate_keys(self.de.duplicate_keys);
+        let lexer = Lexer::resume(self.de.lexer.input(), start);
+        let mut de = Deserializer::from_lexer(lexer, &options);
+        de.duplicates = self.de.duplicates.clone();
+        seed.deserialize(&mut de).map(Some)
+    }
+}
+
+/// Deserializes a map key, or the name of an enum variant.
+struct MapKey<'de> {
+    key: Cow<'de, str>,
 }
 
-fn unwrap_root(value: Value) -> Result<Value> {
-    match value {
-        Value::Map(entries) if entries.len() == 1 => {
-            let (key, inner) = entries.into_iter().next().unwrap();
-            if key.as_ref() == "root" {
-                Ok(inner)
-            } else {
-                Err(Error::Serde(format!("expected root key, got {}", key)))
+/// Implements `deserialize_*` methods for key types written as their string form, such as
+/// integers. Keys that don't parse are handed to the visitor as strings for it to reject.
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self.key.parse() {
+                    Ok(key) => visitor.$visit(key),
+                    Err(_) => self.deserialize_any(visitor),
+                }
             }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self)
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for MapKey<'de> {
+    type Error = Error;
+    type Variant = UnitOnly;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        Ok((seed.deserialize(self)?, UnitOnly))
+    }
+}
+
+/// Variant access for an enum written as a bare string, which can only be a unit variant.
+struct UnitOnly;
+
+impl<'de> de::VariantAccess<'de> for UnitOnly {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// Enum written as a single-entry map, `{ Variant value, }`.
+struct Enum<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let key = Map::new(&mut *self.de, Token::CloseCurly).next_key()?;
+        match key {
+            Some(key) => Ok((seed.deserialize(key)?, self)),
+            None => Err(Error::Serde("expected single key map for enum".to_owned())),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Enum<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Err(Error::Serde("unit variants not supported".to_owned()))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(&mut *self.de)?;
+        self.de.end_entry(Token::CloseCurly)?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.de.deserialize_seq(visitor)?;
+        self.de.end_entry(Token::CloseCurly)?;
+        Ok(value)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.de.deserialize_map(visitor)?;
+        self.de.end_entry(Token::CloseCurly)?;
+        Ok(value)
+    }
+}
+
+impl Value {
+    fn deserialize_number<'de, V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.uncommented() {
+            Value::Number(v) => v.visit(visitor),
+            other => Err(Error::Serde(format!("expected number, got {:?}", other))),
         }
-        Value::Map(_) => Err(Error::Serde("expected single root key".to_owned())),
-        _ => Ok(value),
     }
 }
 
-impl<'de> Deserializer<'de> for Value {
+impl<'de> de::Deserializer<'de> for Value {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::Bool(v) => visitor.visit_bool(v),
-            Value::Number(v) => visitor.visit_f64(v),
+            Value::Null => visitor.visit_unit(),
+            Value::Number(v) => v.visit(visitor),
             Value::String(v) => visitor.visit_string(v),
+            Value::Bytes(v) => visitor.visit_byte_buf(v),
             Value::Array(v) => visitor.visit_seq(ValueSeq::new(v)),
             Value::Map(v) => visitor.visit_map(ValueMap::new(v)),
+            Value::Commented(_) => unreachable!("comments were stripped"),
+            Value::Invalid => Err(Error::Serde(
+                "cannot deserialize an invalid value".to_owned(),
+            )),
         }
     }
 
@@ -51,9 +1389,9 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::Bool(v) => visitor.visit_bool(v),
-            _ => Err(Error::Serde(format!("expected bool, got {:?}", self))),
+            other => Err(Error::Serde(format!("expected bool, got {:?}", other))),
         }
     }
 
@@ -82,10 +1420,14 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Value::Number(v) => visitor.visit_i64(v as i64),
-            _ => Err(Error::Serde(format!("expected number, got {:?}", self))),
-        }
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
@@ -113,10 +1455,14 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Value::Number(v) => visitor.visit_u64(v as u64),
-            _ => Err(Error::Serde(format!("expected number, got {:?}", self))),
-        }
+        self.deserialize_number(visitor)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_number(visitor)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
@@ -130,17 +1476,14 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
-            Value::Number(v) => visitor.visit_f64(v),
-            _ => Err(Error::Serde(format!("expected number, got {:?}", self))),
-        }
+        self.deserialize_number(visitor)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::String(s) => {
                 let mut chars = s.chars();
                 if let Some(c) = chars.next()
@@ -151,7 +1494,7 @@ impl<'de> Deserializer<'de> for Value {
 
                 Err(Error::Serde(format!("expected single char, got {}", s)))
             }
-            _ => Err(Error::Serde(format!("expected string, got {:?}", self))),
+            other => Err(Error::Serde(format!("expected string, got {:?}", other))),
         }
     }
 
@@ -159,9 +1502,9 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::String(v) => visitor.visit_string(v),
-            _ => Err(Error::Serde(format!("expected string, got {:?}", self))),
+            other => Err(Error::Serde(format!("expected string, got {:?}", other))),
         }
     }
 
@@ -172,40 +1515,52 @@ impl<'de> Deserializer<'de> for Value {
         self.deserialize_str(visitor)
     }
 
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::Serde("byte arrays not supported".to_owned()))
+        match self.uncommented() {
+            Value::Bytes(v) => visitor.visit_byte_buf(v),
+            Value::String(v) => visitor.visit_byte_buf(v.into_bytes()),
+            other => Err(Error::Serde(format!(
+                "expected byte string, got {:?}",
+                other
+            ))),
+        }
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::Serde("byte buffers not supported".to_owned()))
+        self.deserialize_bytes(visitor)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        // Glass doesn't have null, so always visit Some
-        visitor.visit_some(self)
+        match self.uncommented() {
+            Value::Null => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
     }
 
-    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::Serde("unit values not supported".to_owned()))
+        match self.uncommented() {
+            Value::Null => visitor.visit_unit(),
+            other => Err(Error::Serde(format!("expected null, got {:?}", other))),
+        }
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        Err(Error::Serde("unit structs not supported".to_owned()))
+        self.deserialize_unit(visitor)
     }
 
     fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
@@ -219,9 +1574,9 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::Array(v) => visitor.visit_seq(ValueSeq::new(v)),
-            _ => Err(Error::Serde(format!("expected array, got {:?}", self))),
+            other => Err(Error::Serde(format!("expected array, got {:?}", other))),
         }
     }
 
@@ -248,9 +1603,9 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::Map(v) => visitor.visit_map(ValueMap::new(v)),
-            _ => Err(Error::Serde(format!("expected map, got {:?}", self))),
+            other => Err(Error::Serde(format!("expected map, got {:?}", other))),
         }
     }
 
@@ -275,7 +1630,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self {
+        match self.uncommented() {
             Value::Map(entries) => {
                 if entries.len() == 1 {
                     let (key, value) = entries.into_iter().next().unwrap();
@@ -285,9 +1640,9 @@ impl<'de> Deserializer<'de> for Value {
                 }
             }
             Value::String(s) => visitor.visit_enum(s.as_str().into_deserializer()),
-            _ => Err(Error::Serde(format!(
+            other => Err(Error::Serde(format!(
                 "expected map or string for enum, got {:?}",
-                self
+                other
             ))),
         }
     }
@@ -308,13 +1663,14 @@ impl<'de> Deserializer<'de> for Value {
 }
 
 struct ValueSeq {
-    values: thin_vec::ThinVec<Value>,
-    index: usize,
+    values: thin_vec::IntoIter<Value>,
 }
 
 impl ValueSeq {
     fn new(values: thin_vec::ThinVec<Value>) -> Self {
-        Self { values, index: 0 }
+        Self {
+            values: values.into_iter(),
+        }
     }
 }
 
@@ -325,27 +1681,22 @@ impl<'de> SeqAccess<'de> for ValueSeq {
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.index < self.values.len() {
-            let value = self.values[self.index].clone();
-            self.index += 1;
-            seed.deserialize(value).map(Some)
-        } else {
-            Ok(None)
+        match self.values.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
         }
     }
 }
 
 struct ValueMap {
-    entries: thin_vec::ThinVec<(Box<str>, Value)>,
-    index: usize,
+    entries: map::IntoIter,
     current_value: Option<Value>,
 }
 
 impl ValueMap {
-    fn new(entries: thin_vec::ThinVec<(Box<str>, Value)>) -> Self {
+    fn new(entries: ast::Map) -> Self {
         Self {
-            entries,
-            index: 0,
+            entries: entries.into_iter(),
             current_value: None,
         }
     }
@@ -358,12 +1709,15 @@ impl<'de> MapAccess<'de> for ValueMap {
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.index < self.entries.len() {
-            let (key, value) = &self.entries[self.index];
-            self.current_value = Some(value.clone());
-            seed.deserialize(key.as_ref().into_deserializer()).map(Some)
-        } else {
-            Ok(None)
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.current_value = Some(value);
+                seed.deserialize(MapKey {
+                    key: Cow::Owned(key.into()),
+                })
+                .map(Some)
+            }
+            None => Ok(None),
         }
     }
 
@@ -372,7 +1726,6 @@ impl<'de> MapAccess<'de> for ValueMap {
         V: de::DeserializeSeed<'de>,
     {
         if let Some(value) = self.current_value.take() {
-            self.index += 1;
             seed.deserialize(value)
         } else {
             Err(Error::Serde("no more values in map".to_owned()))
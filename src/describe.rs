@@ -0,0 +1,318 @@
+//! [`describe`], laying out the keys a Rust type deserializes from as a glass
+//! [schema](crate::schema), from its [`JsonSchema`] implementation.
+
+use std::borrow::Cow;
+
+use schemars::{JsonSchema, SchemaGenerator};
+use serde_json::Value as Json;
+use thin_vec::ThinVec;
+
+use crate::ast::{Comment, Commented, Map, Number, Value};
+use crate::parser::Root;
+use crate::ser::{PrettyConfig, TrailingComma, to_string_pretty};
+
+/// Describes the documents `T` deserializes from as a `schema { ... },` document in the
+/// [schema format](crate::schema), which [`Schema::parse`](crate::schema::Schema::parse) reads
+/// back to check documents against it.
+///
+/// Every key is listed with its type, whether it is optional and its default, under the doc
+/// comments of its field. Keys are in declaration order. Types that contain themselves are only
+/// described down to where they recur, and JSON Schema `pattern`s, which are regular expressions
+/// rather than globs, are left out. Alternatives such as enums with data become a single shape
+/// accepting any of their types, with the keys of all of their maps as optional fields.
+pub fn describe<T: JsonSchema + ?Sized>() -> String {
+    let schema = SchemaGenerator::default().into_root_schema_for::<T>();
+    let root = schema.as_value();
+    let mut describer = Describer {
+        root,
+        expanding: vec!["#"],
+    };
+    let shape = Value::Map(describer.shape(root));
+    let shape = match describer.description(root) {
+        Some(description) => Value::Commented(Box::new(Commented {
+            leading: comments(description),
+            value: shape,
+            trailing: ThinVec::new(),
+        })),
+        None => shape,
+    };
+    let config = PrettyConfig::new()
+        .root(Root::Key(Cow::Borrowed("schema")))
+        .max_width(100)
+        .trailing_comma(TrailingComma::Multiline);
+    to_string_pretty(&shape, config).expect("schemas only hold serializable values")
+}
+
+struct Describer<'a> {
+    root: &'a Json,
+    /// The `$ref`s being expanded, to stop at types that contain themselves.
+    expanding: Vec<&'a str>,
+}
+
+impl<'a> Describer<'a> {
+    /// Translates a JSON Schema into the keywords of a glass shape.
+    fn shape(&mut self, schema: &'a Json) -> Map {
+        let mut shape = Map::new();
+        let Some(object) = schema.as_object() else {
+            return shape;
+        };
+        if let Some(reference) = object.get("$ref").and_then(Json::as_str)
+            && let Some(target) = self.resolve(reference)
+        {
+            if self.expanding.contains(&reference) {
+                if let Some(ty) = target.get("type") {
+                    shape.insert("type", types(ty));
+                }
+            } else {
+                self.expanding.push(reference);
+                shape = self.shape(target);
+                self.expanding.pop();
+            }
+        }
+        for alternatives in ["anyOf", "oneOf"] {
+            if let Some(Json::Array(alternatives)) = object.get(alternatives) {
+                let merged = self.alternatives(alternatives);
+                shape.extend(merged);
+            }
+        }
+        if let Some(Json::Array(all)) = object.get("allOf") {
+            for schema in all {
+                let part = self.shape(schema);
+                shape.extend(part);
+            }
+        }
+
+        if let Some(ty) = object.get("type") {
+            shape.insert("type", types(ty));
+        }
+        if let Some(Json::Array(options)) = object.get("enum") {
+            shape.insert("enum", options.iter().map(value).collect());
+        }
+        if let Some(option) = object.get("const") {
+            shape.insert("enum", Value::Array([value(option)].into_iter().collect()));
+        }
+        for (keyword, glass) in [
+            ("minimum", "min"),
+            ("maximum", "max"),
+            ("minLength", "min_length"),
+            ("maxLength", "max_length"),
+            ("minItems", "min_items"),
+            ("maxItems", "max_items"),
+        ] {
+            if let Some(bound @ Json::Number(_)) = object.get(keyword) {
+                shape.insert(glass, value(bound));
+            }
+        }
+        if let Some(items @ Json::Object(_)) = object.get("items") {
+            shape.insert("items", Value::Map(self.shape(items)));
+        }
+        if let Some(Json::Object(properties)) = object.get("properties") {
+            let required: Vec<_> = match object.get("required") {
+                Some(Json::Array(required)) => required.iter().filter_map(Json::as_str).collect(),
+                _ => Vec::new(),
+            };
+            let mut fields = Map::with_capacity(properties.len());
+            for (key, schema) in properties {
+                let mut field = self.shape(schema);
+                if !required.contains(&key.as_str()) {
+                    field.insert("optional", Value::Bool(true));
+                }
+                let field = match self.description(schema) {
+                    Some(description) => Value::Commented(Box::new(Commented {
+                        leading: comments(description),
+                        value: Value::Map(field),
+                        trailing: ThinVec::new(),
+                    })),
+                    None => Value::Map(field),
+                };
+                fields.insert(key.as_str(), field);
+            }
+            shape.insert("fields", Value::Map(fields));
+            // Unless told otherwise, serde skips keys it doesn't know.
+            shape.insert("additional", Value::Bool(true));
+        }
+        match object.get("additionalProperties") {
+            Some(Json::Bool(allowed)) => {
+                shape.insert("additional", Value::Bool(*allowed));
+            }
+            Some(additional @ Json::Object(_)) => {
+                shape.insert("additional", Value::Map(self.shape(additional)));
+            }
+            _ => {}
+        }
+        if let Some(default) = object.get("default") {
+            shape.insert("default", value(default));
+        }
+        shape
+    }
+
+    /// Merges the shapes of `anyOf` or `oneOf` alternatives into one accepting any of them.
+    fn alternatives(&mut self, alternatives: &'a [Json]) -> Map {
+        let shapes: Vec<Map> = alternatives
+            .iter()
+            .map(|schema| self.shape(schema))
+            .collect();
+        let is_null = |shape: &Map| shape.get("type").and_then(Value::as_str) == Some("null");
+        let nullable = shapes.iter().any(is_null);
+        let mut shapes: Vec<Map> = shapes.into_iter().filter(|shape| !is_null(shape)).collect();
+
+        let mut merged = match shapes.len() {
+            0 => Map::new(),
+            1 => shapes.remove(0),
+            _ => {
+                let mut merged = Map::new();
+                let mut all_types = Vec::new();
+                for shape in &shapes {
+                    let types = match shape.get("type") {
+                        Some(Value::Array(types)) => types.to_vec(),
+                        Some(ty) => vec![ty.clone()],
+                        None => vec![Value::from("any")],
+                    };
+                    for ty in types {
+                        if !all_types.contains(&ty) {
+                            all_types.push(ty);
+                        }
+                    }
+                }
+                if !all_types.contains(&Value::from("any")) {
+                    merged.insert("type", types_value(all_types));
+                }
+                if shapes.iter().all(|shape| shape.contains_key("enum")) {
+                    let options = shapes
+                        .iter()
+                        .filter_map(|shape| shape["enum"].as_array())
+                        .flatten()
+                        .cloned()
+                        .collect();
+                    merged.insert("enum", Value::Array(options));
+                } else if shapes.iter().any(|shape| shape.contains_key("fields")) {
+                    // Fields only apply to maps, so the ones of every map alternative can be
+                    // listed together, none of them required.
+                    let mut fields = Map::new();
+                    let mut additional = false;
+                    for shape in &shapes {
+                        match shape.get("fields").and_then(Value::as_map) {
+                            Some(shape_fields) => {
+                                for (key, field) in shape_fields {
+                                    let mut field = field.clone();
+                                    if let Some(field) = field.as_map_mut() {
+                                        field.insert("optional", Value::Bool(true));
+                                    }
+                                    fields.entry(key).or_insert(field);
+                                }
+                                additional |= shape.get("additional") != Some(&Value::Bool(false));
+                            }
+                            None => additional |= accepts_map(shape),
+                        }
+                    }
+                    merged.insert("fields", Value::Map(fields));
+                    merged.insert("additional", Value::Bool(additional));
+                }
+                merged
+            }
+        };
+
+        if nullable {
+            match merged.get_mut("type") {
+                Some(Value::Array(types)) => types.push(Value::from("null")),
+                Some(ty) => *ty = types_value(vec![ty.clone(), Value::from("null")]),
+                None => {}
+            }
+            if let Some(Value::Array(options)) = merged.get_mut("enum") {
+                options.push(Value::Null);
+            }
+        }
+        merged
+    }
+
+    /// Looks up a `$ref` such as `#/$defs/Server`.
+    fn resolve(&self, reference: &str) -> Option<&'a Json> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+
+    /// Returns the doc comment of a schema, or of the type it refers to, looking through
+    /// `Option`s.
+    fn description(&self, schema: &'a Json) -> Option<&'a str> {
+        let object = schema.as_object()?;
+        if let Some(description) = object.get("description").and_then(Json::as_str) {
+            return Some(description);
+        }
+        if let Some(reference) = object.get("$ref").and_then(Json::as_str) {
+            return self.description(self.resolve(reference)?);
+        }
+        match object
+            .get("anyOf")
+            .and_then(Json::as_array)
+            .map(Vec::as_slice)
+        {
+            Some([some, null] | [null, some]) if null.get("type") == Some(&Json::from("null")) => {
+                self.description(some)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether a shape without fields accepts maps.
+fn accepts_map(shape: &Map) -> bool {
+    match shape.get("type") {
+        Some(Value::Array(types)) => types.contains(&Value::from("map")),
+        Some(ty) => *ty == Value::from("map"),
+        None => true,
+    }
+}
+
+/// Translates a JSON Schema `type`, a name or an array of names.
+fn types(ty: &Json) -> Value {
+    let name = |ty: &Json| {
+        Value::from(match ty.as_str() {
+            Some("object") => "map",
+            Some("boolean") => "bool",
+            Some(name) => name,
+            None => "any",
+        })
+    };
+    match ty {
+        Json::Array(types) => types_value(types.iter().map(name).collect()),
+        ty => name(ty),
+    }
+}
+
+/// Writes a single type as its name, and several as an array.
+fn types_value(mut types: Vec<Value>) -> Value {
+    match types.len() {
+        1 => types.remove(0),
+        _ => Value::Array(types.into()),
+    }
+}
+
+fn value(json: &Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(bool) => Value::Bool(*bool),
+        Json::Number(number) => Value::Number(match (number.as_i64(), number.as_u64()) {
+            (Some(int), _) => Number::from(int),
+            (_, Some(uint)) => Number::from(uint),
+            _ => Number::from(number.as_f64().unwrap_or(f64::NAN)),
+        }),
+        Json::String(string) => Value::String(string.clone()),
+        Json::Array(array) => Value::Array(array.iter().map(value).collect()),
+        Json::Object(object) => Value::Map(
+            object
+                .iter()
+                .map(|(key, json)| (key.as_str().into(), value(json)))
+                .collect(),
+        ),
+    }
+}
+
+/// Turns a doc comment into line comments, one per line.
+fn comments(description: &str) -> ThinVec<Comment> {
+    description
+        .lines()
+        .map(|line| match line {
+            "" => Comment::line(""),
+            line => Comment::line(format!(" {line}")),
+        })
+        .collect()
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::lexer::LexError;
+use crate::lexer::{LexError, Span};
 use crate::parser::ParseError;
 use serde::{de, ser};
 
@@ -12,10 +12,39 @@ pub enum Error {
     CApi(String),
     #[error("serde error: {0}")]
     Serde(String),
+    #[error("I/O error: {0}")]
+    Io(String),
     #[error("lexer error: {0}")]
     LexError(#[from] LexError),
     #[error("parser error: {0}")]
     ParseError(#[from] ParseError),
+    #[error("path error: {0}")]
+    Path(String),
+    #[error("schema error: {0}")]
+    Schema(String),
+}
+
+impl Error {
+    /// Returns where in the input the error occurred, if it came from the lexer or parser.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::LexError(e) => Some(e.span()),
+            Error::ParseError(e) => Some(e.span()),
+            Error::CApi(_) | Error::Serde(_) | Error::Io(_) | Error::Path(_) | Error::Schema(_) => {
+                None
+            }
+        }
+    }
+
+    /// Moves the error's position `by` bytes further into the input, for an error found in a
+    /// slice of it.
+    pub(crate) fn offset(self, by: usize) -> Error {
+        match self {
+            Error::LexError(e) => Error::LexError(e.offset(by)),
+            Error::ParseError(e) => Error::ParseError(e.offset(by)),
+            error => error,
+        }
+    }
 }
 
 impl ser::Error for Error {
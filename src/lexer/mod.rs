@@ -1,155 +1,691 @@
+use std::borrow::Cow;
+use std::fmt;
+
 use thiserror::Error;
-pub use token::Token;
+pub use token::{Span, SpannedToken, Token};
+
+use crate::ast::{CommentKind, Number};
 
 mod token;
 
 #[derive(Debug, Clone, PartialEq, Error)]
 pub enum LexError {
-    #[error("unexpected character: `{0}")]
-    UnexpectedChar(char),
-    #[error("unclosed string")]
-    UnclosedString,
-    #[error("invalid escape sequence")]
-    InvalidEscapeSequence,
+    UnexpectedChar(char, Span),
+    UnclosedString(Span),
+    InvalidEscapeSequence(Span),
+    UnclosedComment(Span),
+    InvalidNumber(Span),
+    /// A `"""` string laid out wrongly, with the reason.
+    InvalidMultilineString(&'static str, Span),
 }
 
-pub fn tokenize(file_content: String) -> Result<Vec<Token>, LexError> {
-    let mut tokens = Vec::new();
-    let chars: Vec<char> = file_content.chars().collect();
-    let mut i = 0;
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedChar(_, span)
+            | LexError::UnclosedString(span)
+            | LexError::InvalidEscapeSequence(span)
+            | LexError::UnclosedComment(span)
+            | LexError::InvalidNumber(span)
+            | LexError::InvalidMultilineString(_, span) => *span,
+        }
+    }
+
+    /// Moves the error `by` bytes further into the input, see [`Span::offset`].
+    pub(crate) fn offset(self, by: usize) -> LexError {
+        match self {
+            LexError::UnexpectedChar(c, span) => LexError::UnexpectedChar(c, span.offset(by)),
+            LexError::UnclosedString(span) => LexError::UnclosedString(span.offset(by)),
+            LexError::InvalidEscapeSequence(span) => {
+                LexError::InvalidEscapeSequence(span.offset(by))
+            }
+            LexError::UnclosedComment(span) => LexError::UnclosedComment(span.offset(by)),
+            LexError::InvalidNumber(span) => LexError::InvalidNumber(span.offset(by)),
+            LexError::InvalidMultilineString(reason, span) => {
+                LexError::InvalidMultilineString(reason, span.offset(by))
+            }
+        }
+    }
+
+    /// Describes the error without its position.
+    pub fn message(&self) -> String {
+        match self {
+            LexError::UnexpectedChar(c, _) => format!("unexpected character: `{c}`"),
+            LexError::UnclosedString(_) => "unclosed string".to_owned(),
+            LexError::InvalidEscapeSequence(_) => "invalid escape sequence".to_owned(),
+            LexError::UnclosedComment(_) => "unclosed block comment".to_owned(),
+            LexError::InvalidNumber(_) => "invalid number".to_owned(),
+            LexError::InvalidMultilineString(reason, _) => {
+                format!("invalid multi-line string: {reason}")
+            }
+        }
+    }
+}
 
-    while i < chars.len() {
-        let c = chars[i];
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.span())
+    }
+}
+
+/// Produces tokens from glass text one at a time.
+#[derive(Clone)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
 
+    /// Starts lexing `input` at `position`, as previously returned by [`Lexer::position`].
+    pub(crate) fn resume(input: &'a str, position: Span) -> Self {
+        Lexer {
+            input,
+            pos: position.start,
+            line: position.line,
+            column: position.column,
+        }
+    }
+
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Where the next token starts, give or take whitespace.
+    pub(crate) fn position(&self) -> Span {
+        self.mark()
+    }
+
+    fn tokenize(mut self) -> Result<Vec<SpannedToken<'a>>, LexError> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let token = self.next_token()?;
+            let eof = token.token == Token::Eof;
+            tokens.push(token);
+            if eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    /// Lexes the next token. Once the input is exhausted this keeps returning [`Token::Eof`].
+    pub fn next_token(&mut self) -> Result<SpannedToken<'a>, LexError> {
         // Skip whitespace
-        if c.is_whitespace() {
-            i += 1;
-            continue;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+
+        let start = self.mark();
+        let Some(c) = self.peek() else {
+            return Ok(self.finish(start, Token::Eof));
+        };
+
+        // Tokenize comments
+        if c == '/' && matches!(self.peek_nth(1), Some('/' | '*')) {
+            let (kind, text) = self.lex_comment(start)?;
+            return Ok(self.finish(start, Token::Comment(kind, Cow::Borrowed(text))));
+        }
+
+        // Tokenize multi-line strings
+        if self.input[self.pos..].starts_with("\"\"\"") {
+            self.bump_n(3);
+            let string = self.lex_multiline_string(start)?;
+            return Ok(self.finish(start, Token::String(Cow::Owned(string))));
+        }
+
+        // Tokenize raw strings
+        if c == 'r'
+            && self.input[self.pos + 1..]
+                .trim_start_matches('#')
+                .starts_with('"')
+        {
+            self.bump();
+            let string = self.lex_raw_string(start)?;
+            return Ok(self.finish(start, Token::String(Cow::Borrowed(string))));
         }
 
         // Tokenize strings
         if c == '"' {
-            let mut string_buf = String::new();
-            i += 1;
-            while i < chars.len() {
-                if chars[i] == '"' {
-                    break;
+            self.bump();
+            let string = self.lex_string(start)?;
+            return Ok(self.finish(start, Token::String(string)));
+        }
+
+        // Tokenize byte strings
+        if c == 'b' && self.peek_nth(1) == Some('"') {
+            self.bump_n(2);
+            let bytes = self.lex_bytes(start)?;
+            return Ok(self.finish(start, Token::Bytes(bytes)));
+        }
+
+        // Tokenize numbers
+        if self.at_number() {
+            return self.lex_number(start);
+        }
+
+        // Tokenize keys
+        if c.is_alphabetic() || c == '_' {
+            self.skip_word();
+
+            let token = match &self.input[start.start..self.pos] {
+                "true" => Token::True,
+                "false" => Token::False,
+                "null" => Token::Null,
+                number @ ("inf" | "nan") => Token::Number(Cow::Borrowed(number)),
+                key => Token::Key(Cow::Borrowed(key)),
+            };
+            return Ok(self.finish(start, token));
+        }
+
+        // Tokenize simple token
+        let token = match c {
+            '{' => Token::OpenCurly,
+            '}' => Token::CloseCurly,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            ',' => Token::Comma,
+            _ => {
+                self.bump();
+                return Err(LexError::UnexpectedChar(c, self.span_from(start)));
+            }
+        };
+        self.bump();
+        Ok(self.finish(start, token))
+    }
+
+    /// Lexes the body of a string literal, the opening quote has already been consumed. The
+    /// result borrows from the input unless the literal contains escape sequences.
+    ///
+    /// On an invalid escape sequence the rest of the string is still consumed, so lexing can
+    /// carry on after it.
+    fn lex_string(&mut self, start: Span) -> Result<Cow<'a, str>, LexError> {
+        let body_start = self.pos;
+
+        loop {
+            match self.peek() {
+                None => return Err(LexError::UnclosedString(self.span_from(start))),
+                Some('"') => {
+                    let body = &self.input[body_start..self.pos];
+                    self.bump();
+                    return Ok(Cow::Borrowed(body));
                 }
+                Some('\\') => break,
+                Some(_) => {
+                    self.bump();
+                }
+            }
+        }
 
-                if chars[i] == '\\' {
-                    i += 1;
-                    if i >= chars.len() {
-                        return Err(LexError::UnclosedString);
+        let mut string_buf = self.input[body_start..self.pos].to_owned();
+        let mut invalid_escape = None;
+
+        loop {
+            let escape_start = self.mark();
+            match self.bump() {
+                None => return Err(LexError::UnclosedString(self.span_from(start))),
+                Some('"') => {
+                    return match invalid_escape {
+                        Some(span) => Err(LexError::InvalidEscapeSequence(span)),
+                        None => Ok(Cow::Owned(string_buf)),
+                    };
+                }
+                Some('\\') => {
+                    if self.peek().is_none() {
+                        return Err(LexError::UnclosedString(self.span_from(start)));
                     }
-                    match chars[i] {
-                        '"' => string_buf.push('"'),
-                        '\\' => string_buf.push('\\'),
-                        'n' => string_buf.push('\n'),
-                        't' => string_buf.push('\t'),
-                        'r' => string_buf.push('\r'),
-                        'x' => {
-                            i += 1;
-                            if i + 1 >= chars.len() {
-                                return Err(LexError::InvalidEscapeSequence);
-                            }
-                            let hex1 = chars[i];
-                            let hex2 = chars[i + 1];
-                            if !hex1.is_ascii_hexdigit() || !hex2.is_ascii_hexdigit() {
-                                return Err(LexError::InvalidEscapeSequence);
-                            }
-                            let hex_str = format!("{}{}", hex1, hex2);
-                            let byte_value = u8::from_str_radix(&hex_str, 16).unwrap();
-                            string_buf.push(byte_value as char);
-                            i += 1;
+                    match self.lex_escape(false).and_then(char::from_u32) {
+                        Some(c) => string_buf.push(c),
+                        None => {
+                            invalid_escape.get_or_insert(self.span_from(escape_start));
                         }
-                        _ => return Err(LexError::InvalidEscapeSequence),
                     }
-                } else {
-                    string_buf.push(chars[i]);
                 }
-                i += 1;
+                Some(c) => string_buf.push(c),
             }
-            if i >= chars.len() {
-                return Err(LexError::UnclosedString);
-            }
-            tokens.push(Token::String(string_buf));
-            i += 1;
-            continue;
         }
+    }
 
-        // Tokenize numbers
-        if c.is_ascii_digit() || (c == '-' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit())
-        {
-            let mut num_buf = String::new();
+    /// Lexes the body of a `r"..."` or `r#"..."#` string, the `r` has already been consumed.
+    /// Nothing is escaped, the string ends at the first quote followed by as many `#` as
+    /// opened it.
+    fn lex_raw_string(&mut self, start: Span) -> Result<&'a str, LexError> {
+        let mut hashes = 0;
+        while self.peek() == Some('#') {
+            self.bump();
+            hashes += 1;
+        }
+        self.bump();
 
-            if c == '-' {
-                num_buf.push(c);
-                i += 1;
+        let body_start = self.pos;
+        let closing = format!("\"{}", "#".repeat(hashes));
+        match self.input[body_start..].find(&closing) {
+            Some(len) => {
+                while self.pos < body_start + len {
+                    self.bump();
+                }
+                self.bump_n(closing.len());
+                Ok(&self.input[body_start..body_start + len])
             }
+            None => {
+                while self.bump().is_some() {}
+                Err(LexError::UnclosedString(self.span_from(start)))
+            }
+        }
+    }
+
+    /// Lexes the body of a `"""` string, the opening quotes have already been consumed.
+    ///
+    /// The text starts on the line after the opening quotes and ends on the line before the
+    /// closing ones. The whitespace in front of the closing quotes is stripped from the start of
+    /// every line, lines that are shorter and only whitespace are empty. Escape sequences work
+    /// as in other strings.
+    fn lex_multiline_string(&mut self, start: Span) -> Result<String, LexError> {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r')) {
+            self.bump();
+        }
+        match self.peek() {
+            Some('\n') => {
+                self.bump();
+            }
+            None => return Err(LexError::UnclosedString(self.span_from(start))),
+            Some(_) => {
+                let span = self.mark();
+                return Err(LexError::InvalidMultilineString(
+                    "text must start on the line after `\"\"\"`",
+                    Span {
+                        end: span.start + 1,
+                        ..span
+                    },
+                ));
+            }
+        }
 
-            while i < chars.len() && chars[i].is_ascii_digit() {
-                num_buf.push(chars[i]);
-                i += 1;
+        // Find the closing quotes, skipping over escaped ones.
+        let body = &self.input[self.pos..];
+        let mut len = 0;
+        loop {
+            if len >= body.len() {
+                while self.bump().is_some() {}
+                return Err(LexError::UnclosedString(self.span_from(start)));
+            }
+            let bytes = body.as_bytes();
+            if bytes[len] == b'\\' {
+                len += 1 + body[len + 1..].chars().next().map_or(0, char::len_utf8);
+            } else if bytes[len..].starts_with(b"\"\"\"") {
+                break;
+            } else {
+                len += 1;
             }
+        }
+
+        let body = &body[..len];
+        let (content_len, indent) = match body.rfind('\n') {
+            Some(newline) => (Some(newline), &body[newline + 1..]),
+            None => (None, body),
+        };
+        if !indent.chars().all(|c| c == ' ' || c == '\t') {
+            let span = self.mark();
+            return Err(LexError::InvalidMultilineString(
+                "closing `\"\"\"` must be on a line of its own",
+                Span {
+                    end: span.start + len + 3,
+                    ..span
+                },
+            ));
+        }
 
-            if i < chars.len() && chars[i] == '.' {
-                num_buf.push(chars[i]);
-                i += 1;
+        let mut string = String::new();
+        let mut invalid_escape = None;
+        if let Some(content_len) = content_len {
+            let content_end = self.pos + content_len;
+            loop {
+                let line_end = self.input[self.pos..content_end]
+                    .find('\n')
+                    .map_or(content_end, |len| self.pos + len);
+                let line = &self.input[self.pos..line_end];
+                let text_end = line_end - usize::from(line.ends_with('\r'));
 
-                while i < chars.len() && chars[i].is_ascii_digit() {
-                    num_buf.push(chars[i]);
-                    i += 1;
+                if line.starts_with(indent) {
+                    self.bump_n(indent.chars().count());
+                } else if line.chars().all(|c| c.is_whitespace()) {
+                    while self.pos < text_end {
+                        self.bump();
+                    }
+                } else {
+                    let span = self.mark();
+                    return Err(LexError::InvalidMultilineString(
+                        "line is indented less than the closing `\"\"\"`",
+                        Span {
+                            end: line_end,
+                            ..span
+                        },
+                    ));
+                }
+
+                while self.pos < text_end {
+                    let escape_start = self.mark();
+                    match self.bump() {
+                        Some('\\') => match self.lex_escape(false).and_then(char::from_u32) {
+                            Some(c) => string.push(c),
+                            None => {
+                                invalid_escape.get_or_insert(self.span_from(escape_start));
+                            }
+                        },
+                        Some(c) => string.push(c),
+                        None => unreachable!("the line ends before the input"),
+                    }
                 }
+                while self.pos <= line_end {
+                    self.bump();
+                }
+                if line_end == content_end {
+                    break;
+                }
+                string.push('\n');
             }
+        }
 
-            tokens.push(Token::Number(num_buf));
-            continue;
+        // Skip the closing line's indentation and quotes.
+        self.bump_n(indent.chars().count() + 3);
+        match invalid_escape {
+            Some(span) => Err(LexError::InvalidEscapeSequence(span)),
+            None => Ok(string),
         }
+    }
 
-        // Tokenize keys
-        if c.is_alphabetic() || c == '_' {
-            let mut key_buf = String::new();
-            key_buf.push(c);
-            i += 1;
+    /// Lexes an escape sequence after its backslash, returning the code point or byte value it
+    /// stands for, or `None` if it is invalid. A closing quote is never consumed.
+    ///
+    /// `\x` escapes are limited to ASCII in strings, code points beyond are written as
+    /// `\u{...}`. Byte strings take any byte value with `\x` and have no `\u{...}`.
+    fn lex_escape(&mut self, byte_string: bool) -> Option<u32> {
+        let hex_digit = |c: Option<char>| c.and_then(|c| c.to_digit(16));
 
-            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
-                key_buf.push(chars[i]);
-                i += 1;
+        let value = match self.peek()? {
+            '"' => '"' as u32,
+            '\\' => '\\' as u32,
+            'n' => '\n' as u32,
+            't' => '\t' as u32,
+            'r' => '\r' as u32,
+            '0' => 0,
+            'x' => {
+                self.bump();
+                let high = hex_digit(self.peek())?;
+                self.bump();
+                let low = hex_digit(self.peek())?;
+                let value = high * 16 + low;
+                if !byte_string && value > 0x7f {
+                    return None;
+                }
+                value
             }
+            'u' if !byte_string => {
+                self.bump();
+                if self.peek() != Some('{') {
+                    return None;
+                }
+                self.bump();
+                let mut value = 0u32;
+                let mut digits = 0;
+                while let Some(digit) = hex_digit(self.peek()) {
+                    self.bump();
+                    value = value.checked_mul(16)? + digit;
+                    digits += 1;
+                }
+                if !(1..=6).contains(&digits) || self.peek() != Some('}') {
+                    return None;
+                }
+                value
+            }
+            _ => {
+                self.bump();
+                return None;
+            }
+        };
+        self.bump();
+        Some(value)
+    }
 
-            match key_buf.as_str() {
-                "true" => tokens.push(Token::True),
-                "false" => tokens.push(Token::False),
-                _ => tokens.push(Token::Key(key_buf)),
+    /// Lexes the body of a byte string literal, the opening `b"` has already been consumed.
+    fn lex_bytes(&mut self, start: Span) -> Result<Cow<'a, [u8]>, LexError> {
+        let body_start = self.pos;
+        let mut bytes = None::<Vec<u8>>;
+        let mut invalid_escape = None;
+
+        loop {
+            let escape_start = self.mark();
+            let byte_pos = self.pos;
+            match self.bump() {
+                None => return Err(LexError::UnclosedString(self.span_from(start))),
+                Some('"') => break,
+                Some('\\') => {
+                    if self.peek().is_none() {
+                        return Err(LexError::UnclosedString(self.span_from(start)));
+                    }
+                    let bytes = bytes.get_or_insert_with(|| {
+                        self.input.as_bytes()[body_start..byte_pos].to_vec()
+                    });
+                    match self.lex_escape(true) {
+                        Some(byte) => bytes.push(byte as u8),
+                        None => {
+                            invalid_escape.get_or_insert(self.span_from(escape_start));
+                        }
+                    }
+                }
+                Some(c) => {
+                    if let Some(bytes) = &mut bytes {
+                        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    }
+                }
             }
-            continue;
         }
 
-        // Tokenize simple token
-        match c {
-            '{' => {
-                tokens.push(Token::OpenCurly);
-                i += 1;
-            }
-            '}' => {
-                tokens.push(Token::CloseCurly);
-                i += 1;
+        if let Some(span) = invalid_escape {
+            return Err(LexError::InvalidEscapeSequence(span));
+        }
+        Ok(match bytes {
+            Some(bytes) => Cow::Owned(bytes),
+            None => Cow::Borrowed(&self.input.as_bytes()[body_start..self.pos - 1]),
+        })
+    }
+
+    /// Lexes a `// line` or `/* block */` comment starting at the current position.
+    fn lex_comment(&mut self, start: Span) -> Result<(CommentKind, &'a str), LexError> {
+        self.bump();
+        let kind = match self.bump() {
+            Some('/') => CommentKind::Line,
+            _ => CommentKind::Block,
+        };
+        let text_start = self.pos;
+
+        match kind {
+            CommentKind::Line => {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+                let text = &self.input[text_start..self.pos];
+                Ok((kind, text.strip_suffix('\r').unwrap_or(text)))
             }
-            '[' => {
-                tokens.push(Token::OpenBracket);
-                i += 1;
+            CommentKind::Block => loop {
+                if self.input[self.pos..].starts_with("*/") {
+                    let text = &self.input[text_start..self.pos];
+                    self.bump();
+                    self.bump();
+                    return Ok((kind, text));
+                }
+                if self.bump().is_none() {
+                    return Err(LexError::UnclosedComment(self.span_from(start)));
+                }
+            },
+        }
+    }
+
+    /// Whether a number starts at the current position: a digit, or a `.`, `+` or `-` leading
+    /// into one, or a signed `inf`.
+    fn at_number(&self) -> bool {
+        let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+        match self.peek() {
+            Some('0'..='9') => true,
+            Some('.') => is_digit(self.peek_nth(1)),
+            Some('+' | '-') => {
+                is_digit(self.peek_nth(1))
+                    || (self.peek_nth(1) == Some('.') && is_digit(self.peek_nth(2)))
+                    || self.input[self.pos + 1..].starts_with("inf")
             }
-            ']' => {
-                tokens.push(Token::CloseBracket);
-                i += 1;
+            _ => false,
+        }
+    }
+
+    /// Lexes a number literal: an optionally signed decimal with an optional fraction and
+    /// exponent, a `0x`, `0o` or `0b` integer, or `inf`. Digits may be separated by `_`.
+    fn lex_number(&mut self, start: Span) -> Result<SpannedToken<'a>, LexError> {
+        if matches!(self.peek(), Some('+' | '-')) {
+            self.bump();
+        }
+
+        if self.input[self.pos..].starts_with("inf") {
+            self.bump_n(3);
+        } else if self.peek() == Some('0') && matches!(self.peek_nth(1), Some('x' | 'o' | 'b')) {
+            // Digits are checked against the radix when parsing.
+            self.bump_n(2);
+            self.skip_word();
+        } else {
+            self.skip_digits();
+            if self.peek() == Some('.') {
+                self.bump();
+                self.skip_digits();
             }
-            ',' => {
-                tokens.push(Token::Comma);
-                i += 1;
+            if matches!(self.peek(), Some('e' | 'E')) {
+                self.bump();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.bump();
+                }
+                self.skip_digits();
             }
-            _ => return Err(LexError::UnexpectedChar(c)),
         }
+
+        // Letters running straight on from the number make the whole thing invalid, rather than
+        // a number followed by a key.
+        self.skip_word();
+
+        let num = &self.input[start.start..self.pos];
+        if Number::parse(num).is_none() {
+            return Err(LexError::InvalidNumber(self.span_from(start)));
+        }
+        Ok(self.finish(start, Token::Number(Cow::Borrowed(num))))
+    }
+
+    fn skip_digits(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '_') {
+            self.bump();
+        }
+    }
+
+    fn skip_word(&mut self) {
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.bump();
+        }
+    }
+
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.bump();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
     }
 
-    Ok(tokens)
+    /// Returns an empty span at the current position.
+    fn mark(&self) -> Span {
+        Span {
+            start: self.pos,
+            end: self.pos,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            end: self.pos,
+            ..start
+        }
+    }
+
+    fn finish(&self, start: Span, token: Token<'a>) -> SpannedToken<'a> {
+        SpannedToken {
+            token,
+            span: self.span_from(start),
+        }
+    }
+}
+
+/// Whether `key` lexes as a [`Token::Key`], so it can be written without quotes.
+pub(crate) fn is_bare_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !matches!(key, "true" | "false" | "null" | "inf" | "nan")
+}
+
+/// Splits `file_content` into tokens. The returned list always ends with a [`Token::Eof`]
+/// positioned at the end of the input.
+pub fn tokenize(file_content: &str) -> Result<Vec<SpannedToken<'_>>, LexError> {
+    Lexer::new(file_content).tokenize()
+}
+
+/// Like [`tokenize`], but carries on after errors. Each error is replaced by a [`Token::Error`]
+/// in the token list and also returned separately.
+pub fn tokenize_recovering(file_content: &str) -> (Vec<SpannedToken<'_>>, Vec<LexError>) {
+    let mut lexer = Lexer::new(file_content);
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    loop {
+        match lexer.next_token() {
+            Ok(token) => {
+                let eof = token.token == Token::Eof;
+                tokens.push(token);
+                if eof {
+                    return (tokens, errors);
+                }
+            }
+            Err(error) => {
+                tokens.push(SpannedToken {
+                    token: Token::Error,
+                    span: error.span(),
+                });
+                errors.push(error);
+            }
+        }
+    }
 }
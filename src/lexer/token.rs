@@ -1,7 +1,12 @@
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 
+use crate::ast::CommentKind;
+
+/// A lexed token. Text is borrowed from the input where possible, only strings containing
+/// escape sequences are owned.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Token {
+pub enum Token<'a> {
     OpenCurly,
     CloseCurly,
     OpenBracket,
@@ -10,15 +15,63 @@ pub enum Token {
 
     True,
     False,
+    Null,
+
+    Key(Cow<'a, str>),
+    Number(Cow<'a, str>),
+    String(Cow<'a, str>),
+    /// A `b"..."` byte string.
+    Bytes(Cow<'a, [u8]>),
 
-    Key(String),
-    Number(String),
-    String(String),
+    Comment(CommentKind, Cow<'a, str>),
 
+    /// Stands in for input that failed to lex, see
+    /// [`tokenize_recovering`](super::tokenize_recovering).
+    Error,
     Eof,
 }
 
-impl Display for Token {
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::OpenCurly => Token::OpenCurly,
+            Token::CloseCurly => Token::CloseCurly,
+            Token::OpenBracket => Token::OpenBracket,
+            Token::CloseBracket => Token::CloseBracket,
+            Token::Comma => Token::Comma,
+
+            Token::True => Token::True,
+            Token::False => Token::False,
+            Token::Null => Token::Null,
+
+            Token::Key(key) => Token::Key(Cow::Owned(key.into_owned())),
+            Token::Number(num) => Token::Number(Cow::Owned(num.into_owned())),
+            Token::String(str) => Token::String(Cow::Owned(str.into_owned())),
+            Token::Bytes(bytes) => Token::Bytes(Cow::Owned(bytes.into_owned())),
+
+            Token::Comment(kind, text) => Token::Comment(kind, Cow::Owned(text.into_owned())),
+
+            Token::Error => Token::Error,
+            Token::Eof => Token::Eof,
+        }
+    }
+
+    /// Describes the token for error messages, as in "found string".
+    pub fn description(&self) -> String {
+        match self {
+            Token::Key(key) => format!("key `{key}`"),
+            Token::Number(num) => format!("number `{num}`"),
+            Token::String(_) => "string".to_owned(),
+            Token::Bytes(_) => "byte string".to_owned(),
+            Token::Comment(..) => "comment".to_owned(),
+            Token::Error => "invalid input".to_owned(),
+            Token::Eof => "end of file".to_owned(),
+            token => format!("`{token}`"),
+        }
+    }
+}
+
+impl Display for Token<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Token::OpenCurly => write!(f, "{{"),
@@ -29,11 +82,51 @@ impl Display for Token {
 
             Token::True => write!(f, "true"),
             Token::False => write!(f, "false"),
+            Token::Null => write!(f, "null"),
 
             Token::Key(key) => write!(f, "{key}"),
             Token::Number(num) => write!(f, "{num}"),
             Token::String(str) => write!(f, "\"{str}\""),
+            Token::Bytes(bytes) => write!(f, "b\"{}\"", bytes.escape_ascii()),
+            Token::Comment(CommentKind::Line, text) => write!(f, "//{text}"),
+            Token::Comment(CommentKind::Block, text) => write!(f, "/*{text}*/"),
+            Token::Error => write!(f, "<error>"),
             Token::Eof => write!(f, "<eof>"),
         }
     }
 }
+
+/// A region of the source text.
+///
+/// `start` and `end` are byte offsets into the input, `line` and `column` are the 1-based
+/// position of `start`. Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns the span `by` bytes further into the input, for one found in a slice of it.
+    pub(crate) fn offset(self, by: usize) -> Span {
+        Span {
+            start: self.start + by,
+            end: self.end + by,
+            ..self
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
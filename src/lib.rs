@@ -1,17 +1,35 @@
 mod ast;
+mod cst;
 mod de;
+#[cfg(feature = "schemars")]
+mod describe;
 mod error;
 mod lexer;
+mod macros;
 mod parser;
+mod path;
+pub mod schema;
 mod ser;
 
 #[cfg(feature = "capi")]
 mod capi;
 
-pub use ast::Value;
-pub use de::from_str;
+pub use ast::{Comment, CommentKind, Commented, Map, Number, Value, map};
+pub use cst::Document;
+pub use de::{
+    Deserializer, ReaderStreamDeserializer, StreamDeserializer, from_str, from_str_with_options,
+    parse_value, parse_value_recovering,
+};
+#[cfg(feature = "schemars")]
+pub use describe::describe;
 pub use error::{Error, Result};
-pub use ser::to_string;
+pub use lexer::Span;
+pub use parser::{Diagnostic, DuplicateKeys, Expected, ParseError, ParseOptions, Root, Severity};
+pub use path::{query, query_with_options};
+pub use ser::{
+    NewlineStyle, PrettyConfig, Serializer, StringStyle, TrailingComma, to_fmt_writer, to_string,
+    to_string_pretty, to_writer, to_writer_pretty,
+};
 
 #[cfg(feature = "capi")]
 pub use capi::*;
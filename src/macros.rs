@@ -0,0 +1,88 @@
+/// Builds a [`Value`](crate::Value) from glass syntax.
+///
+/// The contents are map entries like the ones of a braceless root, or a single value. Maps are
+/// written `{ key value, ... }` with identifiers or string literals as keys, or `(expr)` for
+/// computed ones. Arrays are written `[...]`. `null` is [`Value::Null`](crate::Value::Null), any
+/// other value is a Rust expression converted with `Value::from`.
+///
+/// A single expression that starts with a name followed by more tokens, like `x.len()`, reads as
+/// map entries at the top level, so wrap it in parentheses.
+#[macro_export]
+macro_rules! glass {
+    // Array elements, collected into `[$($elements:expr),*]`.
+    (@array [$($elements:expr),*]) => {
+        $crate::Value::Array([$($elements),*].into_iter().collect())
+    };
+    (@array [$($elements:expr),*] null $(, $($rest:tt)*)?) => {
+        $crate::glass!(@array [$($elements,)* $crate::Value::Null] $($($rest)*)?)
+    };
+    (@array [$($elements:expr),*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::glass!(@array [$($elements,)* $crate::glass!([$($array)*])] $($($rest)*)?)
+    };
+    (@array [$($elements:expr),*] {$($map:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::glass!(@array [$($elements,)* $crate::glass!({$($map)*})] $($($rest)*)?)
+    };
+    (@array [$($elements:expr),*] $value:expr $(, $($rest:tt)*)?) => {
+        $crate::glass!(@array [$($elements,)* $crate::Value::from($value)] $($($rest)*)?)
+    };
+
+    // Map entries, collected as `(key, value),` pairs.
+    (@map [$($entries:tt)*]) => {
+        $crate::Value::Map([$($entries)*].into_iter().collect())
+    };
+    (@map [$($entries:tt)*] $key:ident $($rest:tt)+) => {
+        $crate::glass!(@entry [$($entries)*] (stringify!($key)) $($rest)+)
+    };
+    (@map [$($entries:tt)*] $key:literal $($rest:tt)+) => {
+        $crate::glass!(@entry [$($entries)*] ($key) $($rest)+)
+    };
+    (@map [$($entries:tt)*] ($key:expr) $($rest:tt)+) => {
+        $crate::glass!(@entry [$($entries)*] ($key) $($rest)+)
+    };
+    (@entry [$($entries:tt)*] ($key:expr) null $(, $($rest:tt)*)?) => {
+        $crate::glass!(@map [$($entries)* ($key.into(), $crate::Value::Null),] $($($rest)*)?)
+    };
+    (@entry [$($entries:tt)*] ($key:expr) [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::glass!(
+            @map [$($entries)* ($key.into(), $crate::glass!([$($array)*])),] $($($rest)*)?
+        )
+    };
+    (@entry [$($entries:tt)*] ($key:expr) {$($map:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::glass!(
+            @map [$($entries)* ($key.into(), $crate::glass!({$($map)*})),] $($($rest)*)?
+        )
+    };
+    (@entry [$($entries:tt)*] ($key:expr) $value:expr $(, $($rest:tt)*)?) => {
+        $crate::glass!(
+            @map [$($entries)* ($key.into(), $crate::Value::from($value)),] $($($rest)*)?
+        )
+    };
+
+    () => {
+        $crate::glass!({})
+    };
+    (null) => {
+        $crate::Value::Null
+    };
+    ([$($array:tt)*]) => {
+        $crate::glass!(@array [] $($array)*)
+    };
+    ({$($map:tt)*}) => {
+        $crate::glass!(@map [] $($map)*)
+    };
+    ($key:ident $($rest:tt)+) => {
+        $crate::glass!(@map [] $key $($rest)+)
+    };
+    ($key:literal $($rest:tt)+) => {
+        $crate::glass!(@map [] $key $($rest)+)
+    };
+    (($key:expr) $($rest:tt)+) => {
+        $crate::glass!(@map [] ($key) $($rest)+)
+    };
+    (($value:expr)) => {
+        $crate::Value::from($value)
+    };
+    ($value:expr) => {
+        $crate::Value::from($value)
+    };
+}
@@ -1,80 +1,538 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
+
 use thin_vec::{ThinVec, thin_vec};
 use thiserror::Error;
 
-use crate::{ast::Value, lexer::Token};
+use crate::{
+    ast::{Comment, Commented, Map, Number, Value},
+    lexer::{LexError, Span, SpannedToken, Token},
+};
 
 #[derive(Debug, Clone, PartialEq, Error)]
 pub enum ParseError {
-    #[error("unexpected token: `{0}`")]
-    UnexpectedToken(Token),
-    #[error("unexpected end of file")]
-    UnexpectedEof,
-    #[error("invalid root")]
-    InvalidRoot,
+    UnexpectedToken(Token<'static>, Span, Box<Expected>),
+    UnexpectedEof(Span, Box<Expected>),
+    /// The top-level key isn't the one the [`Root`] mode asks for, described as in "`root`".
+    InvalidRoot(Cow<'static, str>, Span),
+    /// A key appears twice in one map, see [`DuplicateKeys::Error`]. Holds the spans of the
+    /// duplicate and of the first occurrence.
+    DuplicateKey(Box<str>, Span, Span),
+}
+
+/// What the parser was looking for when it found something else.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Expected {
+    /// The alternatives that would have been accepted, such as "`,`" or "a value".
+    pub one_of: Vec<Cow<'static, str>>,
+    /// What the alternatives would have followed, such as "map value".
+    pub after: Option<&'static str>,
+    /// The innermost map or array being parsed, as "map" or "array" and the span of its opening
+    /// delimiter.
+    pub within: Option<(&'static str, Span)>,
+}
+
+impl Expected {
+    fn offset(mut self: Box<Self>, by: usize) -> Box<Self> {
+        if let Some((_, span)) = &mut self.within {
+            *span = span.offset(by);
+        }
+        self
+    }
+
+    pub(crate) fn new(one_of: &[&'static str], after: Option<&'static str>) -> Self {
+        Expected {
+            one_of: one_of.iter().map(|s| Cow::Borrowed(*s)).collect(),
+            after,
+            within: None,
+        }
+    }
+
+    /// Builds the error for finding `tok` instead.
+    pub(crate) fn found(self, tok: SpannedToken<'_>) -> ParseError {
+        match tok.token {
+            Token::Eof => ParseError::UnexpectedEof(tok.span, Box::new(self)),
+            token => ParseError::UnexpectedToken(token.into_owned(), tok.span, Box::new(self)),
+        }
+    }
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken(_, span, _)
+            | ParseError::UnexpectedEof(span, _)
+            | ParseError::InvalidRoot(_, span)
+            | ParseError::DuplicateKey(_, span, _) => *span,
+        }
+    }
+
+    /// Moves the error `by` bytes further into the input, see [`Span::offset`].
+    pub(crate) fn offset(self, by: usize) -> ParseError {
+        match self {
+            ParseError::UnexpectedToken(token, span, expected) => {
+                ParseError::UnexpectedToken(token, span.offset(by), expected.offset(by))
+            }
+            ParseError::UnexpectedEof(span, expected) => {
+                ParseError::UnexpectedEof(span.offset(by), expected.offset(by))
+            }
+            ParseError::InvalidRoot(key, span) => ParseError::InvalidRoot(key, span.offset(by)),
+            ParseError::DuplicateKey(key, span, first) => {
+                ParseError::DuplicateKey(key, span.offset(by), first.offset(by))
+            }
+        }
+    }
+
+    /// Describes what would have been accepted instead, such as "`,`" or "a value".
+    pub fn expected(&self) -> &[Cow<'static, str>] {
+        match self {
+            ParseError::UnexpectedToken(_, _, expected)
+            | ParseError::UnexpectedEof(_, expected) => &expected.one_of,
+            ParseError::InvalidRoot(key, _) => std::slice::from_ref(key),
+            ParseError::DuplicateKey(..) => &[],
+        }
+    }
+
+    /// Describes the error without its position, but with the map or array it occurred in.
+    pub fn message(&self) -> String {
+        match self.context() {
+            Some(context) => format!("{}, {context}", self.headline()),
+            None => self.headline(),
+        }
+    }
+
+    /// Describes the error, as in "expected `,` or `}` after map value, found string".
+    fn headline(&self) -> String {
+        let (found, expected) = match self {
+            ParseError::UnexpectedToken(token, _, expected) => (token.description(), expected),
+            ParseError::UnexpectedEof(_, expected) => ("end of file".to_owned(), expected),
+            ParseError::InvalidRoot(key, _) => return format!("invalid root, expected {key}"),
+            ParseError::DuplicateKey(key, _, _) => return format!("duplicate key `{key}`"),
+        };
+
+        let mut headline = match expected.one_of.as_slice() {
+            [] => return format!("unexpected {found}"),
+            [one] => format!("expected {one}"),
+            [init @ .., last] => format!("expected {} or {last}", init.join(", ")),
+        };
+        if let Some(after) = expected.after {
+            headline.push_str(" after ");
+            headline.push_str(after);
+        }
+        format!("{headline}, found {found}")
+    }
+
+    /// Describes the map or array the error occurred in, as in "in array started at 12:5".
+    fn context(&self) -> Option<String> {
+        match self {
+            ParseError::UnexpectedToken(_, _, expected)
+            | ParseError::UnexpectedEof(_, expected) => {
+                let (kind, start) = expected.within?;
+                Some(format!("in {kind} started at {start}"))
+            }
+            ParseError::InvalidRoot(..) => None,
+            ParseError::DuplicateKey(_, _, first) => Some(format!("first defined at {first}")),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.headline(), self.span())?;
+        if let Some(context) = self.context() {
+            write!(f, ", {context}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A problem found by [`parse_value_recovering`](crate::parse_value_recovering).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    /// Description of the problem, without its position.
+    pub message: String,
+    /// What would have been accepted at `span`, empty when that isn't the problem.
+    pub expected: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The input is invalid.
+    Error,
+    /// The input is valid but likely not what was intended.
+    Warning,
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            span: error.span(),
+            message: error.message(),
+            expected: Vec::new(),
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            span: error.span(),
+            message: error.message(),
+            expected: error.expected().iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// What the top level of a document holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Root {
+    /// A single map under the given key, as in `root { ... },`. The map is the document's value.
+    Key(Cow<'static, str>),
+    /// A single map under any key, as in `package { ... },`. The map is the document's value
+    /// and the key is ignored. Serialized under `root`.
+    AnyKey,
+    /// Any number of `key value,` entries, making up a map without braces.
+    Entries,
+    /// A single value of any kind without a key, as in `{ ... }` or `[1, 2]`.
+    Bare,
+}
+
+impl Default for Root {
+    fn default() -> Self {
+        Root::Key(Cow::Borrowed("root"))
+    }
+}
+
+impl Root {
+    /// Describes the top-level key this mode asks for in error messages.
+    pub(crate) fn expected_key(&self) -> Cow<'static, str> {
+        match self {
+            Root::Key(key) => Cow::Owned(format!("`{key}`")),
+            _ => Cow::Borrowed("a key"),
+        }
+    }
+}
+
+/// What to do when a key appears more than once in the same map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail with [`ParseError::DuplicateKey`].
+    #[default]
+    Error,
+    /// Keep the last entry, at its own position. The recovering parser warns about the
+    /// duplicate.
+    LastWins,
+    /// Keep the first entry and skip the later ones. The recovering parser warns about the
+    /// duplicate.
+    FirstWins,
+    /// Keep one entry at the position of the first, with an array of all the values in order.
+    /// Keys that appear once keep their value as is.
+    Collect,
+}
+
+/// Options controlling how glass text is parsed into a [`Value`].
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    preserve_comments: bool,
+    pub(crate) root: Root,
+    pub(crate) duplicate_keys: DuplicateKeys,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep comments by wrapping the nearest map entry or array element in
+    /// [`Value::Commented`], so they survive being serialized again.
+    ///
+    /// Comments on the lines before an entry become its leading comments. Comments after an
+    /// entry's comma on the same line, and comments before the closing `}` or `]`, become the
+    /// trailing comments of the entry before them. Comments inside an empty map or array are
+    /// discarded.
+    pub fn preserve_comments(mut self, preserve: bool) -> Self {
+        self.preserve_comments = preserve;
+        self
+    }
+
+    /// What the top level of the document holds, `root { ... },` by default.
+    pub fn root(mut self, root: Root) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// What to do with keys that appear more than once in a map, an error by default.
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
 }
 
-struct Parser {
-    tokens: Vec<Token>,
+struct Parser<'a> {
+    tokens: Vec<SpannedToken<'a>>,
     pos: usize,
+    options: ParseOptions,
+    /// Comments skipped over by [`Parser::peek`] that haven't been attached to a value yet,
+    /// with the line they start on.
+    comments: Vec<(Comment, usize)>,
+    /// Whether errors are collected into `diagnostics` instead of ending the parse.
+    recover: bool,
+    diagnostics: Vec<Diagnostic>,
+    eof_reported: bool,
+    /// Closing delimiters of the maps and arrays being parsed together with the spans of their
+    /// opening delimiters, innermost last.
+    open: Vec<(Token<'static>, Span)>,
 }
 
-impl Parser {
-    pub fn parse(tokens: Vec<Token>) -> Result<Value, ParseError> {
-        let mut parser = Parser { tokens, pos: 0 };
-        parser.parse_root()
+/// A map being parsed.
+#[derive(Default)]
+struct Entries {
+    map: Map,
+    /// The span of each entry's key, in the map's order.
+    key_spans: Vec<Span>,
+    /// The keys whose values have been collected into an array, see [`DuplicateKeys::Collect`].
+    collected: HashSet<Box<str>>,
+    /// Set once a value has been replaced in place under [`DuplicateKeys::LastWins`], which
+    /// leaves its entry to be moved to where the key last appears.
+    replaced: bool,
+}
+
+impl Entries {
+    /// Returns the parsed map, with every entry where its key last appears.
+    fn into_map(self) -> Map {
+        if !self.replaced {
+            return self.map;
+        }
+        let mut entries: Vec<_> = self.key_spans.into_iter().zip(self.map).collect();
+        entries.sort_unstable_by_key(|(span, _)| span.start);
+        entries.into_iter().map(|(_, entry)| entry).collect()
     }
+}
 
-    fn parse_root(&mut self) -> Result<Value, ParseError> {
-        let key = self.parse_key()?;
+/// Where parsing resumes after an error in a map or array.
+enum Resync {
+    /// After the comma ending the broken entry.
+    Next,
+    /// At the closing delimiter of the current map or array.
+    Close,
+    /// At the end of the input, or at the closing delimiter of an enclosing map or array. The
+    /// current one is left unclosed.
+    Abandon,
+}
 
-        if key.as_ref() != "root" {
-            return Err(ParseError::InvalidRoot);
+impl<'a> Parser<'a> {
+    fn new(tokens: Vec<SpannedToken<'a>>, options: ParseOptions, recover: bool) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            options,
+            comments: Vec::new(),
+            recover,
+            diagnostics: Vec::new(),
+            eof_reported: false,
+            open: Vec::new(),
         }
+    }
 
-        self.expect(Token::OpenCurly)?;
+    /// Parses the whole input into the document's value, as laid out by the [`Root`] mode.
+    fn parse_document(&mut self) -> Result<Value, ParseError> {
+        let (value, after) = match self.options.root.clone() {
+            Root::Entries => {
+                let (map, _) = self.parse_entries(Token::Eof)?;
+                return Ok(map);
+            }
+            Root::Bare => (self.parse_value(&["a value"], None)?, "root value"),
+            root => (self.parse_root_entry(&root)?, "root map"),
+        };
 
-        let value = self.parse_map()?;
+        let one_of: &[&'static str] = if *self.peek() == Token::Comma {
+            self.advance();
+            &["end of file"]
+        } else {
+            &["`,`", "end of file"]
+        };
+        if *self.peek() != Token::Eof {
+            let err = self.unexpected(one_of, Some(after));
+            self.fail(err)?;
+        }
+        Ok(value)
+    }
 
-        let map = (key, value);
+    /// Parses the `key { ... }` holding the document, returning the map.
+    fn parse_root_entry(&mut self, root: &Root) -> Result<Value, ParseError> {
+        let key_span = self.peek_span();
+        let key = match self.peek().clone() {
+            Token::Key(key) | Token::String(key) => {
+                self.advance();
+                key
+            }
+            _ => {
+                let expected = Expected {
+                    one_of: vec![root.expected_key()],
+                    ..Expected::default()
+                };
+                return Err(self.unexpected_expecting(expected));
+            }
+        };
 
-        self.expect(Token::Comma)?;
+        if let Root::Key(name) = root
+            && key != *name
+        {
+            self.fail(ParseError::InvalidRoot(root.expected_key(), key_span))?;
+        }
 
-        Ok(Value::Map(thin_vec![map]))
+        self.expect(Token::OpenCurly, &["`{`"], Some("root key"))?;
+        self.parse_map()
     }
 
     fn parse_map(&mut self) -> Result<Value, ParseError> {
-        let mut map = ThinVec::new();
+        self.enter(Token::CloseCurly);
+        let (map, closed) = self.parse_entries(Token::CloseCurly)?;
+        self.open.pop();
+        if closed {
+            self.expect(Token::CloseCurly, &["`}`"], None)?;
+        }
+        Ok(map)
+    }
+
+    /// Parses a map's entries up to `close`, which is left for the caller to consume. Also returns
+    /// whether `close` was reached, rather than given up on while recovering.
+    fn parse_entries(&mut self, close: Token<'static>) -> Result<(Value, bool), ParseError> {
+        let mut entries = Entries::default();
+        let key_one_of: &[&'static str] = match close {
+            Token::Eof => &["a key", "end of file"],
+            _ => &["a key", "`}`"],
+        };
+
+        let closed = loop {
+            if *self.peek() == close {
+                break true;
+            }
+            let leading = self.take_comments();
+            let key_span = self.peek_span();
+            let key = match self.parse_key(key_one_of, None) {
+                Ok(key) => key,
+                Err(err) => match self.recover(err)? {
+                    Resync::Next | Resync::Close => continue,
+                    Resync::Abandon => break false,
+                },
+            };
+            let value = match self.parse_value(&["a value"], Some("map key")) {
+                Ok(value) => value,
+                Err(err) => {
+                    let resync = self.recover(err)?;
+                    let value = commented(leading, Value::Invalid, ThinVec::new());
+                    self.insert_entry(&mut entries, key, key_span, value)?;
+                    match resync {
+                        Resync::Next | Resync::Close => continue,
+                        Resync::Abandon => break false,
+                    }
+                }
+            };
 
-        while *self.peek() != Token::CloseCurly {
-            if *self.peek() == Token::Eof {
-                return Err(ParseError::UnexpectedEof);
+            match self.finish_entry(close.clone()) {
+                Ok((trailing, closed)) => {
+                    let value = commented(leading, value, trailing);
+                    self.insert_entry(&mut entries, key, key_span, value)?;
+                    if closed {
+                        break true;
+                    }
+                }
+                Err(err) => {
+                    let value = commented(leading, value, ThinVec::new());
+                    self.insert_entry(&mut entries, key, key_span, value)?;
+                    match self.recover(err)? {
+                        Resync::Next | Resync::Close => continue,
+                        Resync::Abandon => break false,
+                    }
+                }
             }
-            let key = self.parse_key()?;
-            let value = self.parse_value()?;
-            map.push((key, value));
+        };
 
-            self.expect(Token::Comma)?;
-            if *self.peek() == Token::CloseCurly {
-                break;
+        self.comments.clear();
+        Ok((Value::Map(entries.into_map()), closed))
+    }
+
+    /// Adds an entry to a map being parsed as the [`DuplicateKeys`] option says.
+    fn insert_entry(
+        &mut self,
+        entries: &mut Entries,
+        key: Box<str>,
+        key_span: Span,
+        value: Value,
+    ) -> Result<(), ParseError> {
+        let Some(index) = entries.map.get_index_of(&key) else {
+            entries.map.insert(key, value);
+            entries.key_spans.push(key_span);
+            return Ok(());
+        };
+        match self.options.duplicate_keys {
+            DuplicateKeys::Error => {
+                let first = entries.key_spans[index];
+                self.fail(ParseError::DuplicateKey(key, key_span, first))?;
+            }
+            DuplicateKeys::FirstWins => {
+                self.warn_duplicate(&key, key_span, entries.key_spans[index], "is ignored");
+            }
+            DuplicateKeys::LastWins => {
+                self.warn_duplicate(&key, key_span, entries.key_spans[index], "replaces it");
+                // Moving the entry to the end each time would make many duplicates quadratic.
+                *entries.map.get_index_mut(index).expect("key is present").1 = value;
+                entries.key_spans[index] = key_span;
+                entries.replaced = true;
+            }
+            DuplicateKeys::Collect => {
+                let (_, existing) = entries.map.get_index_mut(index).expect("key is present");
+                if entries.collected.insert(key) {
+                    *existing = Value::Array(thin_vec![std::mem::take(existing)]);
+                }
+                let Value::Array(values) = existing else {
+                    unreachable!("collected values are arrays");
+                };
+                values.push(value);
             }
         }
+        Ok(())
+    }
 
-        self.expect(Token::CloseCurly)?;
-        Ok(Value::Map(map))
+    /// Records a warning for a duplicate key that [`DuplicateKeys::FirstWins`] or
+    /// [`DuplicateKeys::LastWins`] drops a value of, when recovering.
+    fn warn_duplicate(&mut self, key: &str, key_span: Span, first: Span, outcome: &str) {
+        if self.recover {
+            self.diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                span: key_span,
+                message: format!("duplicate key `{key}` {outcome}, first defined at {first}"),
+                expected: Vec::new(),
+            });
+        }
     }
 
-    fn parse_key(&mut self) -> Result<Box<str>, ParseError> {
+    fn parse_key(
+        &mut self,
+        one_of: &[&'static str],
+        after: Option<&'static str>,
+    ) -> Result<Box<str>, ParseError> {
         let tok = self.peek().clone();
         match tok {
-            Token::Key(key) => {
+            Token::Key(key) | Token::String(key) => {
                 self.advance();
-                Ok(key.into_boxed_str())
+                Ok(key.into())
             }
-            _ => Err(ParseError::UnexpectedToken(self.peek().clone())),
+            _ => Err(self.unexpected(one_of, after)),
         }
     }
 
-    fn parse_value(&mut self) -> Result<Value, ParseError> {
+    fn parse_value(
+        &mut self,
+        one_of: &[&'static str],
+        after: Option<&'static str>,
+    ) -> Result<Value, ParseError> {
         let tok = self.peek().clone();
         match tok {
             Token::OpenCurly => {
@@ -87,11 +545,15 @@ impl Parser {
             }
             Token::String(str) => {
                 self.advance();
-                Ok(Value::String(str))
+                Ok(Value::String(str.into_owned()))
+            }
+            Token::Bytes(bytes) => {
+                self.advance();
+                Ok(Value::Bytes(bytes.into_owned()))
             }
             Token::Number(num) => {
                 self.advance();
-                Ok(Value::Number(num.parse().unwrap()))
+                Ok(Value::Number(Number::parse(&num).unwrap()))
             }
             Token::True => {
                 self.advance();
@@ -101,47 +563,245 @@ impl Parser {
                 self.advance();
                 Ok(Value::Bool(false))
             }
-            _ => Err(ParseError::UnexpectedToken(self.peek().clone())),
+            Token::Null => {
+                self.advance();
+                Ok(Value::Null)
+            }
+            // The lexer has already reported this.
+            Token::Error => {
+                self.advance();
+                Ok(Value::Invalid)
+            }
+            _ => Err(self.unexpected(one_of, after)),
         }
     }
 
     fn parse_array(&mut self) -> Result<Value, ParseError> {
         let mut array = ThinVec::new();
+        self.enter(Token::CloseBracket);
 
-        while *self.peek() != Token::CloseBracket {
-            if *self.peek() == Token::Eof {
-                return Err(ParseError::UnexpectedEof);
+        let closed = loop {
+            if *self.peek() == Token::CloseBracket {
+                break true;
             }
-            let value = self.parse_value()?;
-            array.push(value);
+            let leading = self.take_comments();
+            let value = match self.parse_value(&["a value", "`]`"], None) {
+                Ok(value) => value,
+                Err(err) => match self.recover(err)? {
+                    Resync::Next => {
+                        array.push(commented(leading, Value::Invalid, ThinVec::new()));
+                        continue;
+                    }
+                    Resync::Close => continue,
+                    Resync::Abandon => break false,
+                },
+            };
 
-            self.expect(Token::Comma)?;
-            if *self.peek() == Token::CloseBracket {
-                break;
+            match self.finish_entry(Token::CloseBracket) {
+                Ok((trailing, closed)) => {
+                    array.push(commented(leading, value, trailing));
+                    if closed {
+                        break true;
+                    }
+                }
+                Err(err) => {
+                    array.push(commented(leading, value, ThinVec::new()));
+                    match self.recover(err)? {
+                        Resync::Next | Resync::Close => continue,
+                        Resync::Abandon => break false,
+                    }
+                }
             }
-        }
+        };
 
-        self.expect(Token::CloseBracket)?;
+        self.open.pop();
+        self.comments.clear();
+        if closed {
+            self.expect(Token::CloseBracket, &["`]`"], None)?;
+        }
         Ok(Value::Array(array))
     }
 
-    fn peek(&mut self) -> &Token {
+    /// Consumes the comma after an entry, which may be left out before the `close` delimiter.
+    /// Returns the entry's trailing comments and whether the enclosing map or array closes next.
+    fn finish_entry(
+        &mut self,
+        close: Token<'static>,
+    ) -> Result<(ThinVec<Comment>, bool), ParseError> {
+        if *self.peek() == close {
+            return Ok((self.take_comments(), true));
+        }
+
+        let (one_of, after): (&[&'static str], _) = match close {
+            Token::CloseCurly => (&["`,`", "`}`"], "map value"),
+            Token::CloseBracket => (&["`,`", "`]`"], "array element"),
+            _ => (&["`,`", "end of file"], "map value"),
+        };
+        self.expect(Token::Comma, one_of, Some(after))?;
+        let comma_line = self.tokens[self.pos - 1].span.line;
+        let closed = *self.peek() == close;
+        Ok((self.take_trailing_comments(comma_line, closed), closed))
+    }
+
+    /// Returns `error` unless recovering, in which case it is recorded instead.
+    fn fail(&mut self, error: ParseError) -> Result<(), ParseError> {
+        if !self.recover {
+            return Err(error);
+        }
+        self.report(error);
+        Ok(())
+    }
+
+    /// Handles an error inside a map or array. When recovering, the error is recorded and
+    /// parsing skips ahead to where it can resume, otherwise the error is returned.
+    fn recover(&mut self, error: ParseError) -> Result<Resync, ParseError> {
+        self.fail(error)?;
+        self.comments.clear();
+        Ok(self.resync())
+    }
+
+    fn report(&mut self, error: ParseError) {
+        match &error {
+            // The lexer has already reported this.
+            ParseError::UnexpectedToken(Token::Error, _, _) => return,
+            ParseError::UnexpectedEof(_, _) => {
+                // Every unclosed map and array runs into the end of the input, only report it
+                // once. An unclosed string or comment also explains it already.
+                let after_error = self.pos > 0 && self.tokens[self.pos - 1].token == Token::Error;
+                if std::mem::replace(&mut self.eof_reported, true) || after_error {
+                    return;
+                }
+            }
+            _ => {}
+        }
+        self.diagnostics.push(error.into());
+    }
+
+    /// Skips past the next `,` of the current map or array, or up to a closing delimiter.
+    fn resync(&mut self) -> Resync {
+        let mut depth = 0usize;
+        loop {
+            match self.peek().clone() {
+                Token::Eof => return Resync::Abandon,
+                Token::OpenCurly | Token::OpenBracket => depth += 1,
+                Token::CloseCurly | Token::CloseBracket if depth > 0 => depth -= 1,
+                close @ (Token::CloseCurly | Token::CloseBracket) => {
+                    if self.open.last().is_some_and(|(closer, _)| *closer == close) {
+                        return Resync::Close;
+                    }
+                    if self.open.iter().any(|(closer, _)| *closer == close) {
+                        return Resync::Abandon;
+                    }
+                    // A stray delimiter that doesn't close anything, skip it.
+                }
+                Token::Comma if depth == 0 => {
+                    self.advance();
+                    return Resync::Next;
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    fn take_comments(&mut self) -> ThinVec<Comment> {
+        self.comments
+            .drain(..)
+            .map(|(comment, _)| comment)
+            .collect()
+    }
+
+    /// Takes the comments belonging after the entry whose comma was just consumed: those on the
+    /// comma's line, or all of them when the enclosing map or array is `closed` right after.
+    fn take_trailing_comments(&mut self, comma_line: usize, closed: bool) -> ThinVec<Comment> {
+        if closed {
+            return self.take_comments();
+        }
+
+        let split = self
+            .comments
+            .iter()
+            .position(|(_, line)| *line > comma_line)
+            .unwrap_or(self.comments.len());
+        self.comments
+            .drain(..split)
+            .map(|(comment, _)| comment)
+            .collect()
+    }
+
+    /// Moves past any comments at the current position, recording them if they are preserved.
+    fn skip_comments(&mut self) {
+        while let Some(SpannedToken {
+            token: Token::Comment(kind, text),
+            span,
+        }) = self.tokens.get(self.pos)
+        {
+            if self.options.preserve_comments {
+                let comment = Comment {
+                    kind: *kind,
+                    text: text.to_string(),
+                };
+                self.comments.push((comment, span.line));
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> &Token<'a> {
+        self.skip_comments();
         if self.pos < self.tokens.len() {
-            &self.tokens[self.pos]
+            &self.tokens[self.pos].token
         } else {
             &Token::Eof
         }
     }
 
-    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
-        if self.peek() == &expected {
+    fn peek_span(&mut self) -> Span {
+        self.skip_comments();
+        match self.tokens.get(self.pos).or(self.tokens.last()) {
+            Some(tok) => tok.span,
+            None => Span::default(),
+        }
+    }
+
+    fn expect(
+        &mut self,
+        token: Token<'static>,
+        one_of: &[&'static str],
+        after: Option<&'static str>,
+    ) -> Result<(), ParseError> {
+        if self.peek() == &token {
             self.advance();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken(self.peek().clone()))
+            Err(self.unexpected(one_of, after))
         }
     }
 
+    /// Builds the error for the token at the current position.
+    fn unexpected(&mut self, one_of: &[&'static str], after: Option<&'static str>) -> ParseError {
+        self.unexpected_expecting(Expected::new(one_of, after))
+    }
+
+    fn unexpected_expecting(&mut self, expected: Expected) -> ParseError {
+        let span = self.peek_span();
+        let token = self.peek().clone();
+        let expected = Expected {
+            within: self
+                .open
+                .last()
+                .map(|(closer, start)| (container(closer), *start)),
+            ..expected
+        };
+        expected.found(SpannedToken { token, span })
+    }
+
+    /// Starts a map or array closed by `closer`, whose opening delimiter was just consumed.
+    fn enter(&mut self, closer: Token<'static>) {
+        let start = self.tokens[self.pos - 1].span;
+        self.open.push((closer, start));
+    }
+
     fn advance(&mut self) {
         if self.pos < self.tokens.len() {
             self.pos += 1;
@@ -149,6 +809,44 @@ impl Parser {
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Value, ParseError> {
-    Parser::parse(tokens)
+fn commented(leading: ThinVec<Comment>, value: Value, trailing: ThinVec<Comment>) -> Value {
+    if leading.is_empty() && trailing.is_empty() {
+        value
+    } else {
+        Value::Commented(Box::new(Commented {
+            leading,
+            value,
+            trailing,
+        }))
+    }
+}
+
+pub fn parse(tokens: Vec<SpannedToken<'_>>, options: ParseOptions) -> Result<Value, ParseError> {
+    Parser::new(tokens, options, false).parse_document()
+}
+
+/// Names the kind of container closed by `closer` for error messages.
+pub(crate) fn container(closer: &Token<'_>) -> &'static str {
+    match closer {
+        Token::CloseBracket => "array",
+        _ => "map",
+    }
+}
+
+/// Parses like [`parse`], collecting every error as a [`Diagnostic`] instead of stopping at the
+/// first. Whatever failed to parse becomes a [`Value::Invalid`], or [`Value::Invalid`] as a whole
+/// if not even the root could be parsed.
+pub fn parse_recovering(
+    tokens: Vec<SpannedToken<'_>>,
+    options: ParseOptions,
+) -> (Value, Vec<Diagnostic>) {
+    let mut parser = Parser::new(tokens, options, true);
+    let value = match parser.parse_document() {
+        Ok(value) => value,
+        Err(err) => {
+            parser.report(err);
+            Value::Invalid
+        }
+    };
+    (value, parser.diagnostics)
 }
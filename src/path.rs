@@ -0,0 +1,568 @@
+//! Paths into values: JSON Pointers, dotted paths such as `servers[2].host`, and queries with
+//! wildcards and filters such as `servers[?port > 8000].host`.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::ast::{Number, Value};
+use crate::de::Deserializer;
+use crate::error::{Error, Result};
+use crate::lexer::{LexError, Lexer, Span, Token};
+use crate::parser::ParseOptions;
+use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+/// Returns every value in a `root { ... },` document that matches `path`, in document order.
+///
+/// The document is read like [`from_str`](crate::from_str) does, without building a [`Value`]
+/// for it. Only matches are kept, and the elements a filter looks at. See [`Value::query`] for
+/// the path syntax.
+pub fn query(input: &str, path: &str) -> Result<Vec<Value>> {
+    query_with_options(input, path, &ParseOptions::default())
+}
+
+/// Like [`query`] for a document laid out as `options` asks for, see [`ParseOptions::root`].
+pub fn query_with_options(input: &str, path: &str, options: &ParseOptions) -> Result<Vec<Value>> {
+    let query = Query::parse(path)?;
+    let mut matches = Vec::new();
+    let mut deserializer = Deserializer::with_options(input, options);
+    deserializer.document_seed(Matches {
+        segments: &query.segments,
+        out: &mut matches,
+    })?;
+    deserializer.end()?;
+    Ok(matches)
+}
+
+impl Value {
+    /// Looks up a value by [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901), such as
+    /// `/dependencies/thiserror` or `/servers/0`. The empty pointer is the value itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut value = self.inner();
+        for token in pointer_tokens(pointer)? {
+            value = match value {
+                Value::Map(_) => value.get(&token)?,
+                Value::Array(array) => array.get(pointer_index(&token)?)?.inner(),
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let mut value = self.inner_mut();
+        for token in pointer_tokens(pointer)? {
+            value = match value {
+                Value::Map(_) => value.get_mut(&token)?,
+                Value::Array(array) => array.get_mut(pointer_index(&token)?)?.inner_mut(),
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Looks up a value by a path of keys and indices such as `servers[2].host`, see
+    /// [`Value::query`]. Returns `None` if there is nothing at the path, or the path is invalid or
+    /// has wildcards or filters.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        let query = Query::parse(path).ok()?;
+        let mut value = self.inner();
+        for segment in &query.segments {
+            value = match segment {
+                Segment::Key(key) => value.get(key)?,
+                Segment::Index(index) => value.as_array()?.get(*index)?.inner(),
+                Segment::Wildcard | Segment::Filter(_) => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let query = Query::parse(path).ok()?;
+        let mut value = self.inner_mut();
+        for segment in &query.segments {
+            value = match segment {
+                Segment::Key(key) => value.get_mut(key)?,
+                Segment::Index(index) => value.as_array_mut()?.get_mut(*index)?.inner_mut(),
+                Segment::Wildcard | Segment::Filter(_) => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Returns every value that matches `path`, in order.
+    ///
+    /// A path is a sequence of
+    ///
+    /// - `.key` or `key` at the start, `."quoted key"` or `["quoted key"]`: the value of that key
+    ///   in a map,
+    /// - `[2]`: the array element at that index,
+    /// - `.*` or `[*]`: every map value or array element,
+    /// - `[?path]` or `[?path op value]`: every map value or array element for which some value
+    ///   at `path`, relative to it, exists and isn't `null` or `false`, or compares to `value`
+    ///   with one of `==`, `!=`, `<`, `<=`, `>` and `>=`. `@` is the element itself, so
+    ///   `ports[?@ > 8000]` filters scalars.
+    ///
+    /// Comparisons are between values of the same kind, numbers compare by value whether they
+    /// are integers or floats.
+    pub fn query(&self, path: &str) -> Result<Vec<&Value>> {
+        let query = Query::parse(path)?;
+        let mut matches = Vec::new();
+        select(&query.segments, self, &mut matches);
+        Ok(matches)
+    }
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens.
+fn pointer_tokens(pointer: &str) -> Option<impl Iterator<Item = String>> {
+    let tokens = match pointer {
+        "" => None,
+        _ => Some(pointer.strip_prefix('/')?.split('/')),
+    };
+    Some(
+        tokens
+            .into_iter()
+            .flatten()
+            .map(|token| token.replace("~1", "/").replace("~0", "~")),
+    )
+}
+
+/// Parses an array index without leading zeros, as JSON Pointer requires.
+fn pointer_index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// A parsed path, see [`Value::query`].
+struct Query {
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+    Filter(Box<Filter>),
+}
+
+struct Filter {
+    path: Vec<Segment>,
+    comparison: Option<(Op, Value)>,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    fn parse(path: &str) -> Result<Query> {
+        let mut parser = QueryParser { path, pos: 0 };
+        let mut segments = Vec::new();
+        if !parser.at_end() && !parser.at(".") && !parser.at("[") {
+            segments.push(Segment::Key(parser.key()?));
+        }
+        parser.segments(&mut segments)?;
+        if !parser.at_end() {
+            return Err(parser.error("`.` or `[`"));
+        }
+        Ok(Query { segments })
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let mut found = Vec::new();
+        select(&self.path, value, &mut found);
+        found.into_iter().any(|found| match &self.comparison {
+            Some((op, operand)) => op.holds(found, operand),
+            None => !matches!(found, Value::Null | Value::Bool(false)),
+        })
+    }
+}
+
+impl Op {
+    fn holds(self, left: &Value, right: &Value) -> bool {
+        let ordering = match (left, right) {
+            (Value::Number(left), Value::Number(right)) => compare_numbers(left, right),
+            (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+            (Value::Bytes(left), Value::Bytes(right)) => Some(left.cmp(right)),
+            (Value::Bool(left), Value::Bool(right)) => Some(left.cmp(right)),
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            _ => None,
+        };
+        match self {
+            Op::Eq => ordering == Some(Ordering::Equal),
+            Op::Ne => ordering != Some(Ordering::Equal),
+            Op::Lt => ordering == Some(Ordering::Less),
+            Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Op::Gt => ordering == Some(Ordering::Greater),
+            Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+pub(crate) fn compare_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    match (left.as_i128(), right.as_i128()) {
+        (Some(left), Some(right)) => Some(left.cmp(&right)),
+        _ => left.as_f64().partial_cmp(&right.as_f64()),
+    }
+}
+
+/// Adds the values under `value` that match `segments` to `matches`.
+fn select<'v>(segments: &[Segment], value: &'v Value, matches: &mut Vec<&'v Value>) {
+    let value = value.inner();
+    let Some((segment, rest)) = segments.split_first() else {
+        matches.push(value);
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(key), Value::Map(map)) => {
+            if let Some(value) = map.get(key) {
+                select(rest, value, matches);
+            }
+        }
+        (Segment::Index(index), Value::Array(array)) => {
+            if let Some(value) = array.get(*index) {
+                select(rest, value, matches);
+            }
+        }
+        (Segment::Wildcard | Segment::Filter(_), Value::Map(_) | Value::Array(_)) => {
+            let children: Box<dyn Iterator<Item = &Value>> = match value {
+                Value::Map(map) => Box::new(map.values()),
+                Value::Array(array) => Box::new(array.iter()),
+                _ => unreachable!("matched a map or an array"),
+            };
+            for child in children {
+                if let Segment::Filter(filter) = segment
+                    && !filter.matches(child.inner())
+                {
+                    continue;
+                }
+                select(rest, child, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct QueryParser<'a> {
+    path: &'a str,
+    pos: usize,
+}
+
+impl QueryParser<'_> {
+    /// Parses `.key`, `[...]` and `.*` segments up to anything else.
+    fn segments(&mut self, segments: &mut Vec<Segment>) -> Result<()> {
+        loop {
+            if self.eat(".") {
+                if self.eat("*") {
+                    segments.push(Segment::Wildcard);
+                } else {
+                    segments.push(Segment::Key(self.key()?));
+                }
+            } else if self.eat("[") {
+                segments.push(self.bracket()?);
+                if !self.eat("]") {
+                    return Err(self.error("`]`"));
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Parses what's between `[` and `]`.
+    fn bracket(&mut self) -> Result<Segment> {
+        if self.eat("*") {
+            return Ok(Segment::Wildcard);
+        }
+        if self.eat("?") {
+            return Ok(Segment::Filter(Box::new(self.filter()?)));
+        }
+        let digits = self.rest().bytes().take_while(u8::is_ascii_digit).count();
+        if digits > 0 {
+            let index = self.rest()[..digits]
+                .parse()
+                .map_err(|_| self.error("an index that fits a `usize`"))?;
+            self.pos += digits;
+            return Ok(Segment::Index(index));
+        }
+        let start = self.pos;
+        match self.token()? {
+            Token::String(key) => Ok(Segment::Key(key.into_owned())),
+            _ => {
+                self.pos = start;
+                Err(self.error("an index, a quoted key, `*` or `?`"))
+            }
+        }
+    }
+
+    fn filter(&mut self) -> Result<Filter> {
+        self.skip_whitespace();
+        let mut path = Vec::new();
+        if !self.eat("@") {
+            path.push(Segment::Key(self.key()?));
+        }
+        self.segments(&mut path)?;
+        self.skip_whitespace();
+
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        let Some(&(_, op)) = ops.iter().find(|(symbol, _)| self.eat(symbol)) else {
+            return Ok(Filter {
+                path,
+                comparison: None,
+            });
+        };
+        self.skip_whitespace();
+        let start = self.pos;
+        let operand = match self.token()? {
+            Token::String(string) => Value::String(string.into_owned()),
+            Token::Bytes(bytes) => Value::Bytes(bytes.into_owned()),
+            Token::Number(number) => Value::Number(Number::parse(&number).unwrap()),
+            Token::True => Value::Bool(true),
+            Token::False => Value::Bool(false),
+            Token::Null => Value::Null,
+            _ => {
+                self.pos = start;
+                return Err(self.error("a string, number, `true`, `false` or `null`"));
+            }
+        };
+        self.skip_whitespace();
+        Ok(Filter {
+            path,
+            comparison: Some((op, operand)),
+        })
+    }
+
+    /// Parses a bare or quoted key.
+    fn key(&mut self) -> Result<String> {
+        let start = self.pos;
+        match self.token()? {
+            Token::Key(key) | Token::String(key) => Ok(key.into_owned()),
+            _ => {
+                self.pos = start;
+                Err(self.error("a key"))
+            }
+        }
+    }
+
+    /// Lexes a glass token at the current position. Whitespace and characters that don't start a
+    /// token lex as [`Token::Error`].
+    fn token(&mut self) -> Result<Token<'_>> {
+        if self.rest().starts_with(char::is_whitespace) {
+            return Ok(Token::Error);
+        }
+        let position = Span {
+            start: self.pos,
+            end: self.pos,
+            line: 1,
+            column: self.column(),
+        };
+        let token = match Lexer::resume(self.path, position).next_token() {
+            Ok(token) => token,
+            // Left for the caller to report as not what it expected.
+            Err(LexError::UnexpectedChar(..)) => return Ok(Token::Error),
+            Err(error) => {
+                return Err(Error::Path(format!(
+                    "{} at column {} of `{}`",
+                    error.message(),
+                    error.span().column,
+                    self.path
+                )));
+            }
+        };
+        self.pos = token.span.end;
+        Ok(token.token)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let eaten = self.at(text);
+        if eaten {
+            self.pos += text.len();
+        }
+        eaten
+    }
+
+    fn at(&self, text: &str) -> bool {
+        self.rest().starts_with(text)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos == self.path.len()
+    }
+
+    fn rest(&self) -> &str {
+        &self.path[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.path.len() - self.rest().trim_start().len();
+    }
+
+    fn column(&self) -> usize {
+        self.path[..self.pos].chars().count() + 1
+    }
+
+    fn error(&self, expected: &str) -> Error {
+        let found = match self.rest().chars().next() {
+            Some(c) => format!("`{c}`"),
+            None => "end of path".to_owned(),
+        };
+        Error::Path(format!(
+            "expected {expected}, found {found} at column {} of `{}`",
+            self.column(),
+            self.path
+        ))
+    }
+}
+
+/// Streams the values that match `segments` out of a deserializer into `out`, skipping over
+/// everything else.
+struct Matches<'q, 'v> {
+    segments: &'q [Segment],
+    out: &'v mut Vec<Value>,
+}
+
+impl Matches<'_, '_> {
+    /// Matches the rest of the path against an element that had to be read whole.
+    fn select_in(&mut self, segments: &[Segment], value: &Value) {
+        let mut matches = Vec::new();
+        select(segments, value, &mut matches);
+        self.out.extend(matches.into_iter().cloned());
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Matches<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        if self.segments.is_empty() {
+            self.out.push(Value::deserialize(deserializer)?);
+            return Ok(());
+        }
+        deserializer.deserialize_any(self)
+    }
+}
+
+macro_rules! skip_scalar {
+    ($($method:ident($ty:ty))*) => {
+        $(
+            fn $method<E>(self, _value: $ty) -> std::result::Result<(), E>
+            where
+                E: de::Error,
+            {
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for Matches<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a glass value")
+    }
+
+    skip_scalar! {
+        visit_bool(bool)
+        visit_i64(i64)
+        visit_u64(u64)
+        visit_i128(i128)
+        visit_f64(f64)
+        visit_str(&str)
+        visit_bytes(&[u8])
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<(), E>
+    where
+        E: de::Error,
+    {
+        Ok(())
+    }
+
+    fn visit_seq<A>(mut self, mut seq: A) -> std::result::Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let (segment, rest) = self.segments.split_first().expect("segments left");
+        if let Segment::Filter(filter) = segment {
+            while let Some(element) = seq.next_element::<Value>()? {
+                if filter.matches(&element) {
+                    self.select_in(rest, &element);
+                }
+            }
+            return Ok(());
+        }
+
+        let mut index = 0;
+        loop {
+            let found = match segment {
+                Segment::Wildcard => true,
+                Segment::Index(wanted) => *wanted == index,
+                Segment::Key(_) | Segment::Filter(_) => false,
+            };
+            let more = if found {
+                let seed = Matches {
+                    segments: rest,
+                    out: &mut *self.out,
+                };
+                seq.next_element_seed(seed)?.is_some()
+            } else {
+                seq.next_element::<IgnoredAny>()?.is_some()
+            };
+            if !more {
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> std::result::Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let (segment, rest) = self.segments.split_first().expect("segments left");
+        while let Some(key) = map.next_key::<String>()? {
+            match segment {
+                Segment::Filter(filter) => {
+                    let value = map.next_value::<Value>()?;
+                    if filter.matches(&value) {
+                        self.select_in(rest, &value);
+                    }
+                }
+                Segment::Key(wanted) if *wanted != key => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                Segment::Index(_) => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                Segment::Key(_) | Segment::Wildcard => {
+                    map.next_value_seed(Matches {
+                        segments: rest,
+                        out: &mut *self.out,
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
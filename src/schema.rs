@@ -0,0 +1,661 @@
+//! Schemas describing the shape of glass documents, themselves written in glass, and a validator
+//! reporting every place a [`Value`] doesn't match one.
+//!
+//! A schema is a document holding a single shape under any key, usually `schema { ... },`. A
+//! shape is a map of these keywords, all of them optional:
+//!
+//! - `type`: one of `any`, `null`, `bool`, `integer`, `number`, `string`, `bytes`, `array` and
+//!   `map`, or an array of them for a value that may be any of those. `number` takes integers
+//!   and floats.
+//! - `enum`: an array of the strings, numbers, bools or `null` the value has to be one of.
+//! - `min` and `max`: inclusive bounds for numbers.
+//! - `pattern`: a glob the whole of a string has to match. `*` matches any run of characters,
+//!   `?` any single one, `[abc]` and `[a-z]` one from a set, `[!abc]` one not in it, and `\`
+//!   makes the next character match itself.
+//! - `min_length` and `max_length`: bounds on the number of characters in a string.
+//! - `min_items` and `max_items`: bounds on the number of elements of an array.
+//! - `items`: the shape of every element of an array.
+//! - `fields`: a map from keys to the shapes of their values. Every key is required unless its
+//!   shape says `optional true`.
+//! - `additional`: whether a map may have keys other than its `fields`, or the shape their values
+//!   must have. Defaults to `false` when there are `fields` and `true` otherwise.
+//! - `default`: the value used when an optional key is left out. It is only there for readers
+//!   and isn't checked.
+//!
+//! Constraints only apply to values of their kind, so a shape with `min` but no `type` accepts
+//! any string. For example:
+//!
+//! ```text
+//! schema {
+//!     type "map",
+//!     fields {
+//!         name { type "string", pattern "[a-z]*" },
+//!         port { type "integer", min 1, max 65535 },
+//!         mode { enum ["dev", "prod"], optional true },
+//!         hosts { type "array", min_items 1, items { type "string" } },
+//!     },
+//! },
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::ast::{Map, Number, Value};
+use crate::de::parse_value;
+use crate::error::{Error, Result};
+use crate::parser::{ParseOptions, Root};
+use crate::path::compare_numbers;
+use crate::ser::{key_literal, quote};
+
+/// A parsed schema, see the [module documentation](self) for the format.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Shape,
+}
+
+/// A place where a value doesn't match its schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Where the offending value is, as a path such as `servers[2].host` that
+    /// [`Value::get_path`] accepts. Empty for the value itself.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl Schema {
+    /// Parses a schema document, a single shape under any key such as `schema { ... },`.
+    pub fn parse(input: &str) -> Result<Schema> {
+        let value = parse_value(input, &ParseOptions::new().root(Root::AnyKey))?;
+        Schema::from_value(&value)
+    }
+
+    /// Reads a schema from the value of its root shape.
+    pub fn from_value(value: &Value) -> Result<Schema> {
+        Ok(Schema {
+            root: Shape::parse(value, "")?,
+        })
+    }
+
+    /// Checks `value` against the schema, returning every violation in document order. The
+    /// value matches if there are none.
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.root.validate(value, "", &mut violations);
+        violations
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Any,
+    Null,
+    Bool,
+    Integer,
+    Number,
+    String,
+    Bytes,
+    Array,
+    Map,
+}
+
+const TYPES: [(&str, Type); 9] = [
+    ("any", Type::Any),
+    ("null", Type::Null),
+    ("bool", Type::Bool),
+    ("integer", Type::Integer),
+    ("number", Type::Number),
+    ("string", Type::String),
+    ("bytes", Type::Bytes),
+    ("array", Type::Array),
+    ("map", Type::Map),
+];
+
+impl Type {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Type::Any => true,
+            Type::Null => value.is_null(),
+            Type::Bool => value.is_bool(),
+            Type::Integer => value.as_number().is_some_and(Number::is_integer),
+            Type::Number => value.is_number(),
+            Type::String => value.is_string(),
+            Type::Bytes => value.is_bytes(),
+            Type::Array => value.is_array(),
+            Type::Map => value.is_map(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Type::Any => "any value",
+            Type::Null => "null",
+            Type::Bool => "a bool",
+            Type::Integer => "an integer",
+            Type::Number => "a number",
+            Type::String => "a string",
+            Type::Bytes => "bytes",
+            Type::Array => "an array",
+            Type::Map => "a map",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Shape {
+    /// Any type if empty.
+    types: Vec<Type>,
+    optional: bool,
+    enumeration: Option<Vec<Value>>,
+    min: Option<Number>,
+    max: Option<Number>,
+    pattern: Option<Pattern>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    items: Option<Box<Shape>>,
+    fields: Vec<(Box<str>, Shape)>,
+    additional: Additional,
+}
+
+#[derive(Debug, Clone)]
+enum Additional {
+    Allowed,
+    Denied,
+    Shape(Box<Shape>),
+}
+
+impl Shape {
+    /// Reads the shape at `path` in the schema.
+    fn parse(value: &Value, path: &str) -> Result<Shape> {
+        let map = value
+            .as_map()
+            .ok_or_else(|| schema_error("expected a map of schema keywords", path))?;
+        let mut shape = Shape {
+            types: Vec::new(),
+            optional: false,
+            enumeration: None,
+            min: None,
+            max: None,
+            pattern: None,
+            min_length: None,
+            max_length: None,
+            min_items: None,
+            max_items: None,
+            items: None,
+            fields: Vec::new(),
+            additional: Additional::Allowed,
+        };
+        let mut additional = None;
+        for (keyword, value) in map {
+            let value = value.inner();
+            let path = &key_path(path, keyword);
+            match keyword {
+                "type" => shape.types = parse_types(value, path)?,
+                "optional" => {
+                    shape.optional = value
+                        .as_bool()
+                        .ok_or_else(|| schema_error("expected a bool", path))?;
+                }
+                "enum" => {
+                    let array = value
+                        .as_array()
+                        .ok_or_else(|| schema_error("expected an array", path))?;
+                    let mut options = Vec::with_capacity(array.len());
+                    for (index, option) in array.iter().enumerate() {
+                        let option = option.inner();
+                        if option.is_map() || option.is_array() || option.is_bytes() {
+                            return Err(schema_error(
+                                "expected a string, number, bool or null",
+                                &index_path(path, index),
+                            ));
+                        }
+                        options.push(option.clone());
+                    }
+                    shape.enumeration = Some(options);
+                }
+                "min" => shape.min = Some(parse_number(value, path)?),
+                "max" => shape.max = Some(parse_number(value, path)?),
+                "pattern" => {
+                    let source = value
+                        .as_str()
+                        .ok_or_else(|| schema_error("expected a string", path))?;
+                    shape.pattern = Some(Pattern::parse(source, path)?);
+                }
+                "min_length" => shape.min_length = Some(parse_count(value, path)?),
+                "max_length" => shape.max_length = Some(parse_count(value, path)?),
+                "min_items" => shape.min_items = Some(parse_count(value, path)?),
+                "max_items" => shape.max_items = Some(parse_count(value, path)?),
+                "items" => shape.items = Some(Box::new(Shape::parse(value, path)?)),
+                "fields" => {
+                    let fields = value
+                        .as_map()
+                        .ok_or_else(|| schema_error("expected a map of keys to shapes", path))?;
+                    shape.fields = fields
+                        .iter()
+                        .map(|(key, field)| {
+                            Ok((key.into(), Shape::parse(field, &key_path(path, key))?))
+                        })
+                        .collect::<Result<_>>()?;
+                }
+                "default" => {}
+                "additional" => {
+                    additional = Some(match value {
+                        Value::Bool(true) => Additional::Allowed,
+                        Value::Bool(false) => Additional::Denied,
+                        Value::Map(_) => Additional::Shape(Box::new(Shape::parse(value, path)?)),
+                        _ => return Err(schema_error("expected a bool or a shape", path)),
+                    });
+                }
+                _ => return Err(schema_error("unknown schema keyword", path)),
+            }
+        }
+        shape.additional = additional.unwrap_or(if shape.fields.is_empty() {
+            Additional::Allowed
+        } else {
+            Additional::Denied
+        });
+        Ok(shape)
+    }
+
+    /// Adds the violations of `value`, found at `path`, to `violations`.
+    fn validate(&self, value: &Value, path: &str, violations: &mut Vec<Violation>) {
+        let value = value.inner();
+        let mut violation = |path: &str, message: String| {
+            violations.push(Violation {
+                path: path.to_owned(),
+                message,
+            });
+        };
+
+        if !self.types.is_empty() && !self.types.iter().any(|ty| ty.matches(value)) {
+            let expected: Vec<_> = self.types.iter().map(|ty| ty.describe()).collect();
+            violation(
+                path,
+                format!(
+                    "expected {}, found {}",
+                    expected.join(" or "),
+                    describe(value)
+                ),
+            );
+            return;
+        }
+        if let Some(options) = &self.enumeration
+            && !options.iter().any(|option| same(option, value))
+        {
+            let options: Vec<_> = options.iter().map(literal).collect();
+            let found = match value {
+                Value::Map(_) | Value::Array(_) | Value::Bytes(_) | Value::Invalid => {
+                    describe(value).to_owned()
+                }
+                _ => literal(value),
+            };
+            violation(
+                path,
+                format!("expected one of {}, found {found}", options.join(", ")),
+            );
+        }
+
+        match value {
+            Value::Number(number) => {
+                if let Some(min) = &self.min
+                    && !matches!(
+                        compare_numbers(number, min),
+                        Some(Ordering::Greater | Ordering::Equal)
+                    )
+                {
+                    violation(path, format!("expected at least {min}, found {number}"));
+                }
+                if let Some(max) = &self.max
+                    && !matches!(
+                        compare_numbers(number, max),
+                        Some(Ordering::Less | Ordering::Equal)
+                    )
+                {
+                    violation(path, format!("expected at most {max}, found {number}"));
+                }
+            }
+            Value::String(string) => {
+                if let Some(pattern) = &self.pattern
+                    && !pattern.matches(string)
+                {
+                    violation(
+                        path,
+                        format!(
+                            "expected a string matching `{}`, found {}",
+                            pattern.source,
+                            quote(string, false)
+                        ),
+                    );
+                }
+                let length = string.chars().count();
+                if let Some(min) = self.min_length
+                    && length < min
+                {
+                    violation(
+                        path,
+                        format!(
+                            "expected at least {}, found {length}",
+                            plural(min, "character")
+                        ),
+                    );
+                }
+                if let Some(max) = self.max_length
+                    && length > max
+                {
+                    violation(
+                        path,
+                        format!(
+                            "expected at most {}, found {length}",
+                            plural(max, "character")
+                        ),
+                    );
+                }
+            }
+            Value::Array(array) => {
+                if let Some(min) = self.min_items
+                    && array.len() < min
+                {
+                    violation(
+                        path,
+                        format!(
+                            "expected at least {}, found {}",
+                            plural(min, "element"),
+                            array.len()
+                        ),
+                    );
+                }
+                if let Some(max) = self.max_items
+                    && array.len() > max
+                {
+                    violation(
+                        path,
+                        format!(
+                            "expected at most {}, found {}",
+                            plural(max, "element"),
+                            array.len()
+                        ),
+                    );
+                }
+                if let Some(items) = &self.items {
+                    for (index, element) in array.iter().enumerate() {
+                        items.validate(element, &index_path(path, index), violations);
+                    }
+                }
+            }
+            Value::Map(map) => self.validate_map(map, path, violations),
+            _ => {}
+        }
+    }
+
+    fn validate_map(&self, map: &Map, path: &str, violations: &mut Vec<Violation>) {
+        for (key, field) in &self.fields {
+            if !field.optional && !map.contains_key(key) {
+                violations.push(Violation {
+                    path: path.to_owned(),
+                    message: format!("missing required key `{key}`"),
+                });
+            }
+        }
+        for (key, value) in map {
+            let path = &key_path(path, key);
+            match self.fields.iter().find(|(field, _)| **field == *key) {
+                Some((_, field)) => field.validate(value, path, violations),
+                None => match &self.additional {
+                    Additional::Allowed => {}
+                    Additional::Denied => violations.push(Violation {
+                        path: path.clone(),
+                        message: "unexpected key".to_owned(),
+                    }),
+                    Additional::Shape(shape) => shape.validate(value, path, violations),
+                },
+            }
+        }
+    }
+}
+
+fn parse_types(value: &Value, path: &str) -> Result<Vec<Type>> {
+    let parse_type = |value: &Value, path: &str| {
+        let name = value
+            .inner()
+            .as_str()
+            .ok_or_else(|| schema_error("expected a type name", path))?;
+        TYPES
+            .iter()
+            .find(|(type_name, _)| *type_name == name)
+            .map(|(_, ty)| *ty)
+            .ok_or_else(|| {
+                let names: Vec<_> = TYPES.iter().map(|(name, _)| *name).collect();
+                schema_error(
+                    &format!(
+                        "unknown type `{name}`, expected one of {}",
+                        names.join(", ")
+                    ),
+                    path,
+                )
+            })
+    };
+    match value {
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(index, value)| parse_type(value, &index_path(path, index)))
+            .collect(),
+        _ => Ok(vec![parse_type(value, path)?]),
+    }
+}
+
+fn parse_number(value: &Value, path: &str) -> Result<Number> {
+    value
+        .as_number()
+        .copied()
+        .ok_or_else(|| schema_error("expected a number", path))
+}
+
+fn parse_count(value: &Value, path: &str) -> Result<usize> {
+    value
+        .as_u64()
+        .and_then(|count| usize::try_from(count).ok())
+        .ok_or_else(|| schema_error("expected a non-negative integer", path))
+}
+
+fn schema_error(message: &str, path: &str) -> Error {
+    if path.is_empty() {
+        Error::Schema(message.to_owned())
+    } else {
+        Error::Schema(format!("{message} at `{path}`"))
+    }
+}
+
+/// Appends a key to a path, bare where the path syntax allows it and quoted otherwise.
+fn key_path(path: &str, key: &str) -> String {
+    let literal = key_literal(key, false);
+    if literal.starts_with('"') {
+        format!("{path}[{literal}]")
+    } else if path.is_empty() {
+        literal.into_owned()
+    } else {
+        format!("{path}.{literal}")
+    }
+}
+
+fn index_path(path: &str, index: usize) -> String {
+    format!("{path}[{index}]")
+}
+
+/// Whether two values are equal, comparing numbers by value.
+fn same(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => {
+            compare_numbers(left, right) == Some(Ordering::Equal)
+        }
+        _ => left == right,
+    }
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Map(_) => "a map",
+        Value::Array(_) => "an array",
+        Value::String(_) => "a string",
+        Value::Bytes(_) => "bytes",
+        Value::Number(number) if number.is_integer() => "an integer",
+        Value::Number(_) => "a float",
+        Value::Bool(_) => "a bool",
+        Value::Null => "null",
+        Value::Commented(commented) => describe(&commented.value),
+        Value::Invalid => "an invalid value",
+    }
+}
+
+/// Writes a scalar value as a glass literal.
+fn literal(value: &Value) -> String {
+    match value {
+        Value::String(string) => quote(string, false),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(bool) => bool.to_string(),
+        _ => "null".to_owned(),
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// A compiled `pattern` glob.
+#[derive(Debug, Clone)]
+struct Pattern {
+    source: String,
+    tokens: Vec<Glob>,
+}
+
+#[derive(Debug, Clone)]
+enum Glob {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    Star,
+    /// `[...]`, with inclusive ranges of characters.
+    Set {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Pattern {
+    fn parse(source: &str, path: &str) -> Result<Pattern> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        while let Some(&c) = chars.get(pos) {
+            pos += 1;
+            tokens.push(match c {
+                '*' => Glob::Star,
+                '?' => Glob::AnyChar,
+                '\\' => {
+                    pos += 1;
+                    Glob::Char(
+                        *chars
+                            .get(pos - 1)
+                            .ok_or_else(|| schema_error("pattern ends in an escape", path))?,
+                    )
+                }
+                '[' => {
+                    let negated = matches!(chars.get(pos), Some('!' | '^'));
+                    if negated {
+                        pos += 1;
+                    }
+                    // A `]` right after the opening bracket is part of the set.
+                    let mut ranges = Vec::new();
+                    let mut first = true;
+                    loop {
+                        let start = match chars.get(pos) {
+                            Some(']') if !first => break,
+                            Some('\\') => chars.get(pos + 1).inspect(|_| pos += 1),
+                            c => c,
+                        };
+                        let start =
+                            *start.ok_or_else(|| schema_error("unclosed `[` in pattern", path))?;
+                        pos += 1;
+                        first = false;
+                        match (chars.get(pos), chars.get(pos + 1)) {
+                            (Some('-'), Some(&end)) if end != ']' => {
+                                ranges.push((start, end));
+                                pos += 2;
+                            }
+                            _ => ranges.push((start, start)),
+                        }
+                    }
+                    pos += 1;
+                    Glob::Set { negated, ranges }
+                }
+                c => Glob::Char(c),
+            });
+        }
+        Ok(Pattern {
+            source: source.to_owned(),
+            tokens,
+        })
+    }
+
+    /// Whether the whole of `string` matches, backtracking to the last `*` on a mismatch.
+    fn matches(&self, string: &str) -> bool {
+        let chars: Vec<char> = string.chars().collect();
+        let (mut token, mut char) = (0, 0);
+        let mut star = None;
+        while char < chars.len() {
+            match self.tokens.get(token) {
+                Some(Glob::Star) => {
+                    star = Some((token + 1, char));
+                    token += 1;
+                }
+                Some(glob) if glob.matches(chars[char]) => {
+                    token += 1;
+                    char += 1;
+                }
+                _ => match star {
+                    Some((after_star, start)) => {
+                        token = after_star;
+                        char = start + 1;
+                        star = Some((after_star, start + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.tokens[token..]
+            .iter()
+            .all(|glob| matches!(glob, Glob::Star))
+    }
+}
+
+impl Glob {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Glob::Char(expected) => c == *expected,
+            Glob::AnyChar => true,
+            Glob::Star => false,
+            Glob::Set { negated, ranges } => {
+                ranges
+                    .iter()
+                    .any(|&(start, end)| (start..=end).contains(&c))
+                    != *negated
+            }
+        }
+    }
+}
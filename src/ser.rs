@@ -1,46 +1,563 @@
+use std::borrow::Cow;
+use std::{fmt, io};
+
+use crate::ast::{COMMENTED, Comment, CommentKind, Number, take_pending_comments};
 use crate::error::{Error, Result};
+use crate::lexer::is_bare_key;
+use crate::parser::Root;
 use serde::ser::{self, Serialize};
+use thin_vec::ThinVec;
+
+/// Controls how [`to_string_pretty`], [`to_writer_pretty`] and [`Serializer::with_config`] lay
+/// out their output.
+///
+/// The default matches [`to_string`]: four space indentation, every array element and map entry
+/// on its own line, and a comma after each of them.
+#[derive(Debug, Clone)]
+pub struct PrettyConfig {
+    indent_width: usize,
+    tabs: bool,
+    compact: bool,
+    max_width: Option<usize>,
+    newline: NewlineStyle,
+    trailing_comma: TrailingComma,
+    ascii_only: bool,
+    string_style: StringStyle,
+    root: Root,
+}
 
-pub struct Serializer {
-    output: String,
+/// Line ending written between lines of multi-line output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NewlineStyle {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// When to write a comma after the last element of an array or map, and after the root value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingComma {
+    #[default]
+    Always,
+    /// Only when the closing `]` or `}` is on a line of its own.
+    Multiline,
+    Never,
+}
+
+/// How string values are written. Map keys are always written bare or as `"..."`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringStyle {
+    /// Always as `"..."` with escape sequences.
+    #[default]
+    Escaped,
+    /// As a `"""` multi-line string when the string contains line breaks, unless the output is
+    /// compact. As a raw `r"..."` string when that saves escaping several quotes or backslashes.
+    Auto,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        PrettyConfig {
+            indent_width: 4,
+            tabs: false,
+            compact: false,
+            max_width: None,
+            newline: NewlineStyle::default(),
+            trailing_comma: TrailingComma::default(),
+            ascii_only: false,
+            string_style: StringStyle::default(),
+            root: Root::default(),
+        }
+    }
+}
+
+impl PrettyConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of spaces per indentation level. Ignored when indenting with tabs.
+    pub fn indent_width(mut self, width: usize) -> Self {
+        self.indent_width = width;
+        self
+    }
+
+    /// Indent with one tab per level instead of spaces.
+    pub fn tabs(mut self, tabs: bool) -> Self {
+        self.tabs = tabs;
+        self
+    }
+
+    /// Write the whole document on a single line, as in `root {a [1, 2], b 3}`.
+    ///
+    /// Line comments can't end before the rest of the line, so they are written as block
+    /// comments.
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Put an array or map on a single line when that line, including its indentation and key,
+    /// fits in `width` characters. Values carrying comments are never collapsed.
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    pub fn newline(mut self, newline: NewlineStyle) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    pub fn trailing_comma(mut self, trailing_comma: TrailingComma) -> Self {
+        self.trailing_comma = trailing_comma;
+        self
+    }
+
+    /// Escape every character above U+007F in strings as `\u{...}`, and quote keys containing
+    /// one. Comments are written as they are.
+    pub fn ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    pub fn string_style(mut self, string_style: StringStyle) -> Self {
+        self.string_style = string_style;
+        self
+    }
+
+    /// How the document's value is written at the top level, `root { ... },` by default. Except
+    /// with [`Root::Bare`] the value has to be a map or struct.
+    pub fn root(mut self, root: Root) -> Self {
+        self.root = root;
+        self
+    }
+}
+
+/// A glass serializer writing into any [`fmt::Write`] sink.
+///
+/// Values are written bare, without the `root` wrapper. Use [`to_string`], [`to_writer`] or
+/// [`to_fmt_writer`] to write a complete document.
+pub struct Serializer<W> {
+    output: W,
+    config: PrettyConfig,
     current_indent: usize,
-    indent_size: usize,
+    /// Whether the innermost open array or map has no entries yet.
+    first: bool,
+    /// Set while measuring whether a value fits on one line, which fails on comments since
+    /// those are never collapsed.
+    collapsing: bool,
+    /// Indentation and key of the entry being serialized. It is written right before the
+    /// entry's value so that leading comments can go in front of it.
+    pending_prefix: Option<String>,
+    /// Trailing comments of the entry being serialized, written after its comma.
+    trailing_comments: ThinVec<Comment>,
+    /// Set until the root map is opened when its entries are written at the top level without
+    /// braces.
+    entries_root: bool,
+    /// Set until the root value is written after a [`Root::Key`] or [`Root::AnyKey`] key, which
+    /// only a map can follow.
+    keyed_root: bool,
 }
 
 pub fn to_string<T>(value: &T) -> Result<String>
 where
     T: Serialize,
 {
-    let mut serializer = Serializer {
-        output: String::new(),
-        current_indent: 0,
-        indent_size: 4,
+    let mut output = String::new();
+    to_fmt_writer(&mut output, value)?;
+    Ok(output)
+}
+
+/// Serializes `value` as a glass document laid out according to `config`.
+pub fn to_string_pretty<T>(value: &T, config: PrettyConfig) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = String::new();
+    write_document(Serializer::with_config(&mut output, config), value)?;
+    Ok(output)
+}
+
+/// Serializes `value` as a glass document into an [`io::Write`] sink.
+///
+/// Output is written in many small pieces, so wrap unbuffered sinks such as files or sockets in
+/// a [`io::BufWriter`].
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    to_writer_pretty(writer, value, PrettyConfig::default())
+}
+
+/// Serializes `value` as a glass document laid out according to `config` into an [`io::Write`]
+/// sink.
+pub fn to_writer_pretty<W, T>(writer: W, value: &T, config: PrettyConfig) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut adapter = IoWriter {
+        inner: writer,
+        error: None,
     };
+    let result = write_document(Serializer::with_config(&mut adapter, config), value);
+    result.map_err(|e| match adapter.error.take() {
+        Some(io_error) => Error::Io(io_error.to_string()),
+        None => e,
+    })
+}
+
+/// Serializes `value` as a glass document into a [`fmt::Write`] sink.
+pub fn to_fmt_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: fmt::Write,
+    T: ?Sized + Serialize,
+{
+    write_document(Serializer::new(writer), value)
+}
 
-    serializer.write_root();
-    value.serialize(&mut serializer)?;
-    serializer.write_sep();
+fn write_document<W, T>(mut serializer: Serializer<W>, value: &T) -> Result<()>
+where
+    W: fmt::Write,
+    T: ?Sized + Serialize,
+{
+    match serializer.config.root.clone() {
+        Root::Entries => {
+            serializer.entries_root = true;
+            value.serialize(&mut serializer)?;
+            serializer.write_trailing_comments()
+        }
+        Root::Bare => {
+            serializer.serialize_entry_value(value)?;
+            serializer.write_trailing_comments()
+        }
+        root => {
+            let key = match &root {
+                Root::Key(key) => key.as_ref(),
+                _ => "root",
+            };
+            let key = key_literal(key, serializer.config.ascii_only);
+            serializer.pending_prefix = Some(format!("{key} "));
+            serializer.keyed_root = true;
+            serializer.serialize_entry_value(value)?;
+            serializer.write_sep()
+        }
+    }
+}
+
+/// Forwards [`fmt::Write`] calls to an [`io::Write`], keeping the underlying error around since
+/// [`fmt::Error`] can't carry it.
+struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
 
-    Ok(serializer.output)
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
 }
 
-impl Serializer {
-    fn write_indent(&mut self) {
-        for _ in 0..self.current_indent * self.indent_size {
-            self.output.push(' ');
+/// Collects output until more than `remaining` characters have been written.
+struct LimitedWriter {
+    output: String,
+    remaining: usize,
+}
+
+impl fmt::Write for LimitedWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let len = s.chars().count();
+        if len > self.remaining {
+            return Err(fmt::Error);
+        }
+        self.remaining -= len;
+        self.output.push_str(s);
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, PrettyConfig::default())
+    }
+
+    pub fn with_config(writer: W, config: PrettyConfig) -> Self {
+        Serializer {
+            output: writer,
+            config,
+            current_indent: 0,
+            first: true,
+            collapsing: false,
+            pending_prefix: None,
+            trailing_comments: ThinVec::new(),
+            entries_root: false,
+            keyed_root: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<()> {
+        if self.entries_root {
+            return Err(Error::Serde(
+                "only a map or struct can be written as top-level entries".to_owned(),
+            ));
+        }
+        if self.keyed_root {
+            if !s.starts_with('{') {
+                return Err(Error::Serde(
+                    "only a map or struct can be written after a root key, other values need `Root::Bare`"
+                        .to_owned(),
+                ));
+            }
+            self.keyed_root = false;
+        }
+        if let Some(prefix) = self.pending_prefix.take() {
+            self.write_raw(&prefix)?;
+        }
+        self.write_raw(s)
+    }
+
+    /// Writes `s` without flushing the pending entry prefix.
+    fn write_raw(&mut self, s: &str) -> Result<()> {
+        self.output
+            .write_str(s)
+            .map_err(|_| Error::Io("failed to write output".to_owned()))
+    }
+
+    fn indent(&self) -> String {
+        self.indent_at(self.current_indent)
+    }
+
+    fn indent_at(&self, level: usize) -> String {
+        if self.config.compact {
+            String::new()
+        } else if self.config.tabs {
+            "\t".repeat(level)
+        } else {
+            " ".repeat(level * self.config.indent_width)
+        }
+    }
+
+    fn newline(&self) -> &'static str {
+        match self.config.newline {
+            NewlineStyle::Lf => "\n",
+            NewlineStyle::CrLf => "\r\n",
+        }
+    }
+
+    fn write_indent(&mut self) -> Result<()> {
+        let indent = self.indent();
+        self.write_str(&indent)
+    }
+
+    /// Writes the opening delimiter of an array or map.
+    ///
+    /// The entries of the root map are written without braces or indentation for
+    /// [`Root::Entries`], which is the only way entries end up at indentation level zero.
+    fn open(&mut self, delimiter: &str) -> Result<()> {
+        if self.entries_root && delimiter == "{" {
+            self.entries_root = false;
+        } else {
+            self.write_str(delimiter)?;
+            self.current_indent += 1;
+        }
+        self.first = true;
+        Ok(())
+    }
+
+    /// Writes the closing delimiter of an array or map, after finishing its last entry.
+    fn close(&mut self, delimiter: &str) -> Result<()> {
+        if self.current_indent == 0 {
+            if !self.first {
+                self.write_sep()?;
+            }
+            self.first = false;
+            return Ok(());
+        }
+        self.current_indent -= 1;
+        if !self.first {
+            self.end_entry(true)?;
+        } else if !self.config.compact {
+            self.write_str(self.newline())?;
+        }
+        self.write_indent()?;
+        self.write_str(delimiter)?;
+        self.first = false;
+        Ok(())
+    }
+
+    /// Starts an array element, or a map entry when `key` is given.
+    fn begin_entry(&mut self, key: Option<&str>) -> Result<()> {
+        if !self.first {
+            self.end_entry(false)?;
+        } else if !self.config.compact && self.current_indent > 0 {
+            self.write_str(self.newline())?;
+        }
+        self.first = false;
+
+        let mut prefix = self.indent();
+        if let Some(key) = key {
+            prefix.push_str(&key_literal(key, self.config.ascii_only));
+            prefix.push(' ');
+        }
+        self.pending_prefix = Some(prefix);
+        Ok(())
+    }
+
+    /// Finishes the previous entry with its comma and trailing comments. The `last` entry of an
+    /// array or map only gets a comma if the trailing comma style asks for one.
+    fn end_entry(&mut self, last: bool) -> Result<()> {
+        if !last || self.wants_trailing_comma() {
+            self.write_str(",")?;
+        }
+        self.write_trailing_comments()?;
+        if !self.config.compact {
+            self.write_str(self.newline())?;
+        } else if !last {
+            self.write_str(" ")?;
+        }
+        Ok(())
+    }
+
+    fn wants_trailing_comma(&self) -> bool {
+        match self.config.trailing_comma {
+            TrailingComma::Always => true,
+            TrailingComma::Multiline => !self.config.compact,
+            TrailingComma::Never => false,
+        }
+    }
+
+    /// Writes `v` as a `"""` string with its lines indented one level deeper than the entry.
+    fn write_multiline_str(&mut self, v: &str) -> Result<()> {
+        let indent = self.indent_at(self.current_indent + 1);
+        let mut literal = String::from("\"\"\"");
+        for line in v.split('\n') {
+            literal.push_str(self.newline());
+            if !line.is_empty() {
+                literal.push_str(&indent);
+            }
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => literal.push_str("\\\\"),
+                    // Only a run of three quotes needs breaking up.
+                    '"' if chars.clone().take(2).eq(['"', '"']) => literal.push_str("\\\""),
+                    '\t' => literal.push(c),
+                    '\x00'..='\x1f' => literal.push_str(&format!("\\x{:02x}", c as u32)),
+                    '\u{80}'.. if self.config.ascii_only => {
+                        literal.push_str(&format!("\\u{{{:x}}}", c as u32));
+                    }
+                    _ => literal.push(c),
+                }
+            }
+        }
+        literal.push_str(self.newline());
+        literal.push_str(&indent);
+        literal.push_str("\"\"\"");
+        self.write_str(&literal)
+    }
+
+    /// Serializes the value of an entry, on a single line if it fits in `max_width`.
+    fn serialize_entry_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.collapsed(value) {
+            Some(collapsed) => self.write_str(&collapsed),
+            None => value.serialize(self),
+        }
+    }
+
+    /// Serializes `value` in compact form, if `max_width` is set and the line would fit.
+    fn collapsed<T>(&self, value: &T) -> Option<String>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.config.compact || self.entries_root {
+            return None;
         }
+        let max_width = self.config.max_width?;
+        let prefix_width = self
+            .pending_prefix
+            .as_deref()
+            .map_or(0, |p| p.chars().count());
+        // Leave room for the comma after the value.
+        let remaining = max_width.checked_sub(prefix_width + 1)?;
+
+        let config = PrettyConfig {
+            compact: true,
+            max_width: None,
+            ..self.config.clone()
+        };
+        let writer = LimitedWriter {
+            output: String::new(),
+            remaining,
+        };
+        let mut serializer = Serializer::with_config(writer, config);
+        serializer.collapsing = true;
+        value.serialize(&mut serializer).ok()?;
+        Some(serializer.output.output)
+    }
+
+    /// Renders a comment, turning line comments into block comments in compact output.
+    fn comment(&self, comment: &Comment) -> Result<String> {
+        if !self.config.compact || comment.kind == CommentKind::Block {
+            return Ok(comment.to_string());
+        }
+        if comment.text.contains("*/") {
+            return Err(Error::Serde(format!(
+                "line comment `{comment}` can't be written in compact output"
+            )));
+        }
+        Ok(Comment::block(comment.text.clone()).to_string())
     }
 
-    pub(crate) fn write_root(&mut self) {
-        self.output.push_str("root ");
+    fn write_leading_comments(&mut self, comments: &[Comment]) -> Result<()> {
+        let indent = self.indent();
+        for comment in comments {
+            let comment = self.comment(comment)?;
+            if self.config.compact {
+                self.write_raw(&format!("{comment} "))?;
+            } else {
+                self.write_raw(&format!("{indent}{comment}{}", self.newline()))?;
+            }
+        }
+        Ok(())
     }
 
-    pub(crate) fn write_sep(&mut self) {
-        self.output.push(',');
+    fn write_trailing_comments(&mut self) -> Result<()> {
+        let indent = self.indent();
+        for (i, comment) in std::mem::take(&mut self.trailing_comments)
+            .iter()
+            .enumerate()
+        {
+            let comment = self.comment(comment)?;
+            if i == 0 || self.config.compact {
+                self.write_str(&format!(" {comment}"))?;
+            } else {
+                self.write_str(&format!("{}{indent}{comment}", self.newline()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_sep(&mut self) -> Result<()> {
+        if self.wants_trailing_comma() {
+            self.write_str(",")?;
+        }
+        self.write_trailing_comments()
     }
 }
 
-impl ser::Serializer for &mut Serializer {
+impl<W: fmt::Write> ser::Serializer for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -53,7 +570,7 @@ impl ser::Serializer for &mut Serializer {
     type SerializeStructVariant = Self;
 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.output.push_str(if v { "true" } else { "false" });
+        self.write_str(if v { "true" } else { "false" })?;
         Ok(())
     }
 
@@ -70,7 +587,7 @@ impl ser::Serializer for &mut Serializer {
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.output.push_str(&v.to_string());
+        self.write_str(&v.to_string())?;
         Ok(())
     }
 
@@ -87,16 +604,29 @@ impl ser::Serializer for &mut Serializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.output.push_str(&v.to_string());
+        self.write_str(&v.to_string())?;
         Ok(())
     }
 
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.write_str(&v.to_string())
+    }
+
+    /// Integers are read back as `i128`, so larger ones are rejected rather than written as
+    /// literals that don't parse.
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        match i128::try_from(v) {
+            Ok(v) => self.serialize_i128(v),
+            Err(_) => Err(Error::Serde(format!("integer {v} is out of range"))),
+        }
+    }
+
     fn serialize_f32(self, v: f32) -> Result<()> {
         self.serialize_f64(f64::from(v))
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
-        self.output.push_str(&v.to_string());
+        self.write_str(&Number::from(v).to_string())?;
         Ok(())
     }
 
@@ -105,30 +635,37 @@ impl ser::Serializer for &mut Serializer {
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        self.output.push('"');
-        for c in v.chars() {
-            match c {
-                '"' => self.output.push_str("\\\""),
-                '\\' => self.output.push_str("\\\\"),
-                '\n' => self.output.push_str("\\n"),
-                '\t' => self.output.push_str("\\t"),
-                '\r' => self.output.push_str("\\r"),
-                '\x00'..='\x1f' => {
-                    self.output.push_str(&format!("\\x{:02x}", c as u32));
-                }
-                _ => self.output.push(c),
+        if self.config.string_style == StringStyle::Auto {
+            if v.contains('\n') && !self.config.compact {
+                return self.write_multiline_str(v);
+            }
+            if let Some(raw) = raw_literal(v, self.config.ascii_only) {
+                return self.write_str(&raw);
             }
         }
-        self.output.push('"');
-        Ok(())
-    }
-
-    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
-        Err(Error::Serde("byte arrays not supported".to_owned()))
+        self.write_str(&quote(v, self.config.ascii_only))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        let mut escaped = String::with_capacity(v.len() + 3);
+        escaped.push_str("b\"");
+        for &byte in v {
+            match byte {
+                b'"' => escaped.push_str("\\\""),
+                b'\\' => escaped.push_str("\\\\"),
+                b'\n' => escaped.push_str("\\n"),
+                b'\t' => escaped.push_str("\\t"),
+                b'\r' => escaped.push_str("\\r"),
+                b' '..=b'~' => escaped.push(byte as char),
+                _ => escaped.push_str(&format!("\\x{byte:02x}")),
+            }
+        }
+        escaped.push('"');
+        self.write_str(&escaped)
     }
 
     fn serialize_none(self) -> Result<()> {
-        Err(Error::Serde("null values not supported".to_owned()))
+        self.serialize_unit()
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<()>
@@ -139,11 +676,11 @@ impl ser::Serializer for &mut Serializer {
     }
 
     fn serialize_unit(self) -> Result<()> {
-        Err(Error::Serde("unit values not supported".to_owned()))
+        self.write_str("null")
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
-        Err(Error::Serde("unit structs not supported".to_owned()))
+        self.serialize_unit()
     }
 
     fn serialize_unit_variant(
@@ -155,10 +692,22 @@ impl ser::Serializer for &mut Serializer {
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
+        if name == COMMENTED
+            && let Some((leading, trailing)) = take_pending_comments()
+        {
+            if self.collapsing {
+                return Err(Error::Serde("comments are never collapsed".to_owned()));
+            }
+            self.write_leading_comments(&leading)?;
+            // The comments sit outside the value, so it can still be collapsed.
+            self.serialize_entry_value(value)?;
+            self.trailing_comments = trailing;
+            return Ok(());
+        }
         value.serialize(self)
     }
 
@@ -172,14 +721,14 @@ impl ser::Serializer for &mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.output.push_str(variant);
-        self.output.push(' ');
-        value.serialize(self)
+        self.open("{")?;
+        self.begin_entry(Some(variant))?;
+        self.serialize_entry_value(value)?;
+        self.close("}")
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.output.push_str("[\n");
-        self.current_indent += 1;
+        self.open("[")?;
         Ok(self)
     }
 
@@ -199,21 +748,22 @@ impl ser::Serializer for &mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Err(Error::Serde("tuple variants not supported".to_owned()))
+        self.open("{")?;
+        self.begin_entry(Some(variant))?;
+        self.open("[")?;
+        Ok(self)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.output.push_str("{\n");
-        self.current_indent += 1;
+        self.open("{")?;
         Ok(self)
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        self.output.push_str("{\n");
-        self.current_indent += 1;
+        self.open("{")?;
         Ok(self)
     }
 
@@ -221,14 +771,17 @@ impl ser::Serializer for &mut Serializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Err(Error::Serde("struct variants not supported".to_owned()))
+        self.open("{")?;
+        self.begin_entry(Some(variant))?;
+        self.open("{")?;
+        Ok(self)
     }
 }
 
-impl ser::SerializeSeq for &mut Serializer {
+impl<W: fmt::Write> ser::SerializeSeq for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -236,21 +789,16 @@ impl ser::SerializeSeq for &mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.write_indent();
-        value.serialize(&mut **self)?;
-        self.output.push_str(",\n");
-        Ok(())
+        self.begin_entry(None)?;
+        self.serialize_entry_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.current_indent -= 1;
-        self.write_indent();
-        self.output.push(']');
-        Ok(())
+        self.close("]")
     }
 }
 
-impl ser::SerializeTuple for &mut Serializer {
+impl<W: fmt::Write> ser::SerializeTuple for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -266,7 +814,7 @@ impl ser::SerializeTuple for &mut Serializer {
     }
 }
 
-impl ser::SerializeTupleStruct for &mut Serializer {
+impl<W: fmt::Write> ser::SerializeTupleStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -282,23 +830,25 @@ impl ser::SerializeTupleStruct for &mut Serializer {
     }
 }
 
-impl ser::SerializeTupleVariant for &mut Serializer {
+/// Written as `{ Variant [a, b], }`.
+impl<W: fmt::Write> ser::SerializeTupleVariant for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::Serde("tuple variants not supported".to_owned()))
+        ser::SerializeSeq::serialize_element(self, value)
     }
 
     fn end(self) -> Result<()> {
-        Err(Error::Serde("tuple variants not supported".to_owned()))
+        self.close("]")?;
+        self.close("}")
     }
 }
 
-impl ser::SerializeMap for &mut Serializer {
+impl<W: fmt::Write> ser::SerializeMap for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -306,32 +856,24 @@ impl ser::SerializeMap for &mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.write_indent();
         match key.serialize(MapKeySerializer)? {
-            MapKey::String(s) => self.output.push_str(&s),
+            MapKey::String(s) => self.begin_entry(Some(&s)),
         }
-        self.output.push(' ');
-        Ok(())
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)?;
-        self.output.push_str(",\n");
-        Ok(())
+        self.serialize_entry_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.current_indent -= 1;
-        self.write_indent();
-        self.output.push('}');
-        Ok(())
+        self.close("}")
     }
 }
 
-impl ser::SerializeStruct for &mut Serializer {
+impl<W: fmt::Write> ser::SerializeStruct for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -339,42 +881,91 @@ impl ser::SerializeStruct for &mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.write_indent();
-        self.output.push_str(key);
-        self.output.push(' ');
-        value.serialize(&mut **self)?;
-        self.output.push_str(",\n");
-        Ok(())
+        self.begin_entry(Some(key))?;
+        self.serialize_entry_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.current_indent -= 1;
-        self.write_indent();
-        self.output.push('}');
-        Ok(())
+        self.close("}")
     }
 }
 
-impl ser::SerializeStructVariant for &mut Serializer {
+/// Written as `{ Variant { field value, }, }`.
+impl<W: fmt::Write> ser::SerializeStructVariant for &mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::Serde("struct variants not supported".to_owned()))
+        ser::SerializeStruct::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<()> {
-        Err(Error::Serde("struct variants not supported".to_owned()))
+        self.close("}")?;
+        self.close("}")
+    }
+}
+
+/// Writes `v` as a string literal, escaping non-ASCII characters if `ascii_only` is set.
+pub(crate) fn quote(v: &str, ascii_only: bool) -> String {
+    let mut escaped = String::with_capacity(v.len() + 2);
+    escaped.push('"');
+    for c in v.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\x00'..='\x1f' => {
+                escaped.push_str(&format!("\\x{:02x}", c as u32));
+            }
+            '\u{80}'.. if ascii_only => {
+                escaped.push_str(&format!("\\u{{{:x}}}", c as u32));
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Writes `v` as a raw string if it has several quotes or backslashes to escape and nothing a
+/// raw string can't hold.
+fn raw_literal(v: &str, ascii_only: bool) -> Option<String> {
+    let escapes = v.chars().filter(|c| matches!(c, '"' | '\\')).count();
+    if escapes < 2 || v.chars().any(char::is_control) || (ascii_only && !v.is_ascii()) {
+        return None;
+    }
+
+    let mut hashes = String::new();
+    while v.contains(&format!("\"{hashes}")) {
+        hashes.push('#');
     }
+    Some(format!("r{hashes}\"{v}\"{hashes}"))
+}
+
+/// Writes a map key bare when it reads back as one, and quoted otherwise.
+pub(crate) fn key_literal(key: &str, ascii_only: bool) -> Cow<'_, str> {
+    if is_bare_key(key) && (!ascii_only || key.is_ascii()) {
+        Cow::Borrowed(key)
+    } else {
+        Cow::Owned(quote(key, ascii_only))
+    }
+}
+
+fn key_error() -> Error {
+    Error::Serde("map keys must be strings, integers, bools, chars or unit variants".to_owned())
 }
 
 enum MapKey {
     String(String),
 }
 
+/// Serializes map keys to their string form. Integers, bools, chars and unit variants are
+/// written as strings so they can be used as keys, and parsed back when deserializing.
 struct MapKeySerializer;
 
 impl ser::Serializer for MapKeySerializer {
@@ -389,52 +980,60 @@ impl ser::Serializer for MapKeySerializer {
     type SerializeStruct = ser::Impossible<MapKey, Error>;
     type SerializeStructVariant = ser::Impossible<MapKey, Error>;
 
-    fn serialize_bool(self, _v: bool) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_bool(self, v: bool) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_i32(self, v: i32) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_i64(self, v: i64) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_u8(self, v: u8) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_i64(self, _v: i64) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_u16(self, v: u16) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_u8(self, _v: u8) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_u32(self, v: u32) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_u64(self, v: u64) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_u32(self, _v: u32) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_i128(self, v: i128) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_u128(self, v: u128) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
     fn serialize_f32(self, _v: f32) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_f64(self, _v: f64) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
-    fn serialize_char(self, _v: char) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+    fn serialize_char(self, v: char) -> Result<MapKey> {
+        Ok(MapKey::String(v.to_string()))
     }
 
     fn serialize_str(self, v: &str) -> Result<MapKey> {
@@ -442,42 +1041,42 @@ impl ser::Serializer for MapKeySerializer {
     }
 
     fn serialize_bytes(self, _v: &[u8]) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_none(self) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_some<T>(self, _value: &T) -> Result<MapKey>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_unit(self) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_unit_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<MapKey> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Ok(MapKey::String(variant.to_owned()))
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<MapKey>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<MapKey>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        value.serialize(self)
     }
 
     fn serialize_newtype_variant<T>(
@@ -490,15 +1089,15 @@ impl ser::Serializer for MapKeySerializer {
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_tuple_struct(
@@ -506,7 +1105,7 @@ impl ser::Serializer for MapKeySerializer {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_tuple_variant(
@@ -516,15 +1115,15 @@ impl ser::Serializer for MapKeySerializer {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 
     fn serialize_struct_variant(
@@ -534,6 +1133,6 @@ impl ser::Serializer for MapKeySerializer {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Err(Error::Serde("map keys must be strings".to_owned()))
+        Err(key_error())
     }
 }
@@ -1,6 +1,18 @@
-use glass::{Value, from_str, to_string};
+use glass::schema::Schema;
+use glass::{
+    Comment, Commented, Deserializer, Document, DuplicateKeys, Error, Map, NewlineStyle,
+    ParseError, ParseOptions, PrettyConfig, ReaderStreamDeserializer, Root, Serializer, Severity,
+    StringStyle, TrailingComma, Value, from_str, from_str_with_options, glass, map, parse_value,
+    parse_value_recovering, to_fmt_writer, to_string, to_string_pretty, to_writer,
+};
 use proptest::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_bytes::{ByteBuf, Bytes};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::rc::Rc;
 use thin_vec::{ThinVec, thin_vec};
 
 #[test]
@@ -13,10 +25,10 @@ fn parses_simple_example() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![(
+    let expected = Value::Map(Map::from([(
         "hello".into(),
         Value::String("world".to_string()),
-    )]);
+    )]));
 
     assert_eq!(ast, expected);
 }
@@ -34,16 +46,16 @@ fn parses_nested_example() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
+    let expected = Value::Map(Map::from([
         ("hello".into(), Value::String("world".to_string())),
         (
             "nested".into(),
-            Value::Map(thin_vec![(
+            Value::Map(Map::from([(
                 "hello".into(),
                 Value::String("world".to_string()),
-            )])
+            )])),
         ),
-    ]);
+    ]));
 
     assert_eq!(ast, expected);
 }
@@ -61,7 +73,7 @@ fn parses_array_example() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
+    let expected = Value::Map(Map::from([
         (
             "hello".into(),
             Value::Array(thin_vec![
@@ -71,15 +83,15 @@ fn parses_array_example() {
         ),
         (
             "nested".into(),
-            Value::Map(thin_vec![(
+            Value::Map(Map::from([(
                 "hello".into(),
                 Value::Array(thin_vec![
                     Value::String("world".to_string()),
                     Value::String("foo".to_string()),
                 ]),
-            ),])
+            )])),
         ),
-    ]);
+    ]));
 
     assert_eq!(ast, expected);
 }
@@ -95,14 +107,37 @@ fn parses_booleans() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
+    let expected = Value::Map(Map::from([
         ("is_true".into(), Value::Bool(true)),
         ("is_false".into(), Value::Bool(false)),
-    ]);
+    ]));
 
     assert_eq!(ast, expected);
 }
 
+#[test]
+fn parses_null() {
+    let input = r#"
+        root {
+            nothing null,
+            list [null, true,],
+        },
+    "#;
+
+    let ast: Value = from_str(input).unwrap();
+
+    let expected = Value::Map(Map::from([
+        ("nothing".into(), Value::Null),
+        (
+            "list".into(),
+            Value::Array(thin_vec![Value::Null, Value::Bool(true)]),
+        ),
+    ]));
+
+    assert_eq!(ast, expected);
+    assert_eq!(parse_value(input, &ParseOptions::new()).unwrap(), expected);
+}
+
 #[test]
 fn parses_numbers() {
     let input = r#"
@@ -116,14 +151,188 @@ fn parses_numbers() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
-        ("integer".into(), Value::Number(123.0)),
-        ("negative".into(), Value::Number(-456.0)),
-        ("float".into(), Value::Number(12.34)),
-        ("negative_float".into(), Value::Number(-56.78)),
-    ]);
+    let expected = Value::Map(Map::from([
+        ("integer".into(), Value::Number(123.into())),
+        ("negative".into(), Value::Number((-456).into())),
+        ("float".into(), Value::Number(12.34.into())),
+        ("negative_float".into(), Value::Number((-56.78).into())),
+    ]));
+
+    assert_eq!(ast, expected);
+}
+
+#[test]
+fn keeps_integers_exact() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ids {
+        max: u64,
+        min: i64,
+        wide: i128,
+        ratio: f64,
+        whole: f64,
+    }
+
+    let ids = Ids {
+        max: u64::MAX,
+        min: i64::MIN,
+        wide: i128::MIN,
+        ratio: 0.25,
+        whole: 3.0,
+    };
+
+    let serialized = to_string(&ids).unwrap();
+    assert!(serialized.contains("max 18446744073709551615,"));
+    assert!(serialized.contains("min -9223372036854775808,"));
+    assert!(serialized.contains("whole 3.0,"));
+    assert_eq!(from_str::<Ids>(&serialized).unwrap(), ids);
+
+    let value = parse_value(&serialized, &ParseOptions::new()).unwrap();
+    assert_eq!(Ids::deserialize(value.clone()).unwrap(), ids);
+    assert_eq!(to_string(&value).unwrap(), serialized);
+
+    let Value::Map(entries) = value else {
+        panic!("expected a map");
+    };
+    assert_eq!(
+        *entries.get_index(0).unwrap().1,
+        Value::Number(u64::MAX.into())
+    );
+    assert_eq!(*entries.get_index(3).unwrap().1, Value::Number(0.25.into()));
+    assert_eq!(*entries.get_index(4).unwrap().1, Value::Number(3.0.into()));
+    assert_ne!(*entries.get_index(4).unwrap().1, Value::Number(3.into()));
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wide {
+        value: u128,
+    }
+    let wide = Wide {
+        value: i128::MAX as u128,
+    };
+    let serialized = to_string(&wide).unwrap();
+    assert_eq!(from_str::<Wide>(&serialized).unwrap(), wide);
+    let err = to_string(&Wide { value: u128::MAX }).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("serde error: integer {} is out of range", u128::MAX)
+    );
+}
+
+#[test]
+fn rejects_lossy_integers() {
+    #[derive(Debug, Deserialize)]
+    struct Small {
+        #[allow(dead_code)]
+        value: u8,
+    }
+
+    assert!(from_str::<Small>("root { value 256, },").is_err());
+    assert!(from_str::<Small>("root { value 1.5, },").is_err());
+    assert!(from_str::<Small>("root { value -1, },").is_err());
+}
+
+#[test]
+fn parses_number_syntax() {
+    let input = r#"
+        root {
+            exponent 1e9,
+            negative_exponent -2.5E-3,
+            hex 0xFF,
+            octal -0o17,
+            binary 0b1010_1010,
+            separated 1_000_000,
+            separated_hex 0xdead_beef,
+            separated_float 1_0.2_5e1_0,
+            plus +5,
+            leading_dot .5,
+            infinity inf,
+            negative_infinity -inf,
+        },
+    "#;
+
+    let ast: Value = from_str(input).unwrap();
+
+    let expected = Value::Map(Map::from([
+        ("exponent".into(), Value::Number(1e9.into())),
+        ("negative_exponent".into(), Value::Number((-2.5e-3).into())),
+        ("hex".into(), Value::Number(255.into())),
+        ("octal".into(), Value::Number((-15).into())),
+        ("binary".into(), Value::Number(170.into())),
+        ("separated".into(), Value::Number(1_000_000.into())),
+        (
+            "separated_hex".into(),
+            Value::Number(0xdead_beef_u32.into()),
+        ),
+        ("separated_float".into(), Value::Number(10.25e10.into())),
+        ("plus".into(), Value::Number(5.into())),
+        ("leading_dot".into(), Value::Number(0.5.into())),
+        ("infinity".into(), Value::Number(f64::INFINITY.into())),
+        (
+            "negative_infinity".into(),
+            Value::Number(f64::NEG_INFINITY.into()),
+        ),
+    ]));
 
     assert_eq!(ast, expected);
+    assert_eq!(parse_value(input, &ParseOptions::new()).unwrap(), expected);
+
+    let nan: f64 = match from_str::<Value>("root { n nan, },").unwrap() {
+        Value::Map(entries) => f64::deserialize(entries.get_index(0).unwrap().1.clone()).unwrap(),
+        _ => panic!("expected a map"),
+    };
+    assert!(nan.is_nan());
+}
+
+#[test]
+fn rejects_invalid_numbers() {
+    for input in [
+        "0x",
+        "0xG1",
+        "0b102",
+        "1e",
+        "12abc",
+        "-infinity",
+        "0x1_0000_0000_0000_0000_0000_0000_0000_0000",
+        "99999999999999999999999999999999999999999",
+        "-170141183460469231731687303715884105729",
+        "1_",
+        "1__0",
+        "0x_1",
+        "0b1_",
+        "1_.5",
+        "1._5",
+        "1_e5",
+        "1e_5",
+    ] {
+        let err = from_str::<Value>(&format!("root {{ n {input}, }},")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lexer error: invalid number at 1:10",
+            "{input}"
+        );
+    }
+}
+
+#[test]
+fn serializes_special_floats() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Floats {
+        values: Vec<f64>,
+    }
+
+    let floats = Floats {
+        values: vec![f64::INFINITY, f64::NEG_INFINITY, 1e300, -1.5e-7, 2.0, 0.1],
+    };
+
+    let serialized = to_string(&floats).unwrap();
+    assert!(serialized.contains("    inf,\n        -inf,\n        1e300,\n        -1.5e-7,\n"));
+    assert_eq!(from_str::<Floats>(&serialized).unwrap(), floats);
+
+    let nan = to_string(&Floats {
+        values: vec![f64::NAN],
+    })
+    .unwrap();
+    assert!(nan.contains("    nan,\n"));
+    assert!(from_str::<Floats>(&nan).unwrap().values[0].is_nan());
 }
 
 #[test]
@@ -138,11 +347,11 @@ fn parses_escaped_strings() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
+    let expected = Value::Map(Map::from([
         ("escaped".into(), Value::String("line1\nline2".to_string())),
         ("quoted".into(), Value::String("\"quoted\"".to_string())),
         ("backslash".into(), Value::String("\\".to_string())),
-    ]);
+    ]));
 
     assert_eq!(ast, expected);
 }
@@ -160,16 +369,205 @@ fn parses_hex_escape_sequences() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
+    let expected = Value::Map(Map::from([
         ("hex_lower".into(), Value::String("ABC".to_string())),
         ("hex_upper".into(), Value::String("zxy".to_string())),
         ("hex_mixed".into(), Value::String("A012Z".to_string())),
         ("hex_nul".into(), Value::String("\0".to_string())),
-    ]);
+    ]));
+
+    assert_eq!(ast, expected);
+}
 
+#[test]
+fn parses_unicode_and_nul_escapes() {
+    let input = r#"root { s "caf\u{e9} \u{1F600}\0", b b"\xc3\xa9\0", },"#;
+
+    let ast: Value = from_str(input).unwrap();
+
+    let expected = Value::Map(Map::from([
+        ("s".into(), Value::String("café 😀\0".to_string())),
+        ("b".into(), Value::Bytes(vec![0xc3, 0xa9, 0])),
+    ]));
     assert_eq!(ast, expected);
 }
 
+#[test]
+fn rejects_invalid_escapes() {
+    for escape in [
+        r"\xc3\xa9",
+        r"\x80",
+        r"\u{110000}",
+        r"\u{d800}",
+        r"\u{}",
+        r"\u{1234567}",
+        r"\u1234",
+        r"\u{12",
+    ] {
+        let err = from_str::<Value>(&format!(r#"root {{ s "{escape}", }},"#)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lexer error: invalid escape sequence at 1:11",
+            "{escape}"
+        );
+    }
+
+    let err = from_str::<Value>(r#"root { b b"\u{41}", },"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "lexer error: invalid escape sequence at 1:12"
+    );
+}
+
+#[test]
+fn ascii_only_output() {
+    let value = Value::Map(Map::from([
+        ("name".into(), Value::String("Zoë 😀\u{7f}".to_string())),
+        ("ключ".into(), Value::Bool(true)),
+    ]));
+
+    let glass = to_string_pretty(&value, PrettyConfig::new().ascii_only(true)).unwrap();
+    assert_eq!(
+        glass,
+        "root {\n    name \"Zo\\u{eb} \\u{1f600}\u{7f}\",\n    \"\\u{43a}\\u{43b}\\u{44e}\\u{447}\" true,\n},"
+    );
+    assert_eq!(from_str::<Value>(&glass).unwrap(), value);
+}
+
+#[test]
+fn parses_raw_strings() {
+    let input = r####"
+        root {
+            path r"C:\temp\new",
+            quote r#"say "hi""#,
+            fence r##"a "# b"##,
+        },
+    "####;
+
+    let value: Value = from_str(input).unwrap();
+    assert_eq!(
+        value,
+        Value::Map(Map::from([
+            ("path".into(), Value::String(r"C:\temp\new".to_string())),
+            ("quote".into(), Value::String(r#"say "hi""#.to_string())),
+            ("fence".into(), Value::String(r##"a "# b"##.to_string())),
+        ]))
+    );
+}
+
+#[test]
+fn parses_multiline_strings() {
+    let input = "root {\n    text \"\"\"\n        first\n          indented\n\n        tab\\there \\\"\"\"\n        \"\"\",\n    crlf \"\"\"\r\n    a\r\n    b\r\n    \"\"\",\n},";
+
+    let value: Value = from_str(input).unwrap();
+    assert_eq!(
+        value,
+        Value::Map(Map::from([
+            (
+                "text".into(),
+                Value::String("first\n  indented\n\ntab\there \"\"\"".to_string())
+            ),
+            ("crlf".into(), Value::String("a\nb".to_string())),
+        ]))
+    );
+}
+
+#[test]
+fn parses_non_ascii_multiline_strings() {
+    let input = "root {\n    s \"\"\"\n    café \\\"\"\"\n      wörld\n    \"\"\",\n},";
+    assert_eq!(
+        from_str::<Value>(input).unwrap(),
+        glass!({ s "café \"\"\"\n  wörld" })
+    );
+}
+
+#[test]
+fn rejects_invalid_multiline_strings() {
+    for (input, message) in [
+        (
+            "root { s \"\"\"text\n    \"\"\", },",
+            "lexer error: invalid multi-line string: text must start on the line after `\"\"\"` at 1:13",
+        ),
+        (
+            "root { s \"\"\"\n    a\n  b\n    \"\"\", },",
+            "lexer error: invalid multi-line string: line is indented less than the closing `\"\"\"` at 3:1",
+        ),
+        (
+            "root { s \"\"\"\n    a\n    b \"\"\", },",
+            "lexer error: invalid multi-line string: closing `\"\"\"` must be on a line of its own at 2:1",
+        ),
+        (
+            "root { s \"\"\"\n    a\n",
+            "lexer error: unclosed string at 1:10",
+        ),
+        (
+            "root {\n    s \"\"\"\n    \\é\n    \"\"\",\n},",
+            "lexer error: invalid escape sequence at 3:5",
+        ),
+        (
+            "root { s \"\"\"\n    é\\é",
+            "lexer error: unclosed string at 1:10",
+        ),
+    ] {
+        let err = from_str::<Value>(input).unwrap_err();
+        assert_eq!(err.to_string(), message, "{input:?}");
+    }
+}
+
+#[test]
+fn auto_string_style() {
+    let value = Value::Map(Map::from([
+        ("path".into(), Value::String(r"C:\temp\new".to_string())),
+        ("quote".into(), Value::String(r#"say "hi""#.to_string())),
+        ("one".into(), Value::String(r"a\b".to_string())),
+        (
+            "text".into(),
+            Value::String("first\n  indented\n\nend \"\"\" \\\r".to_string()),
+        ),
+        (
+            "list".into(),
+            Value::Array(thin_vec![Value::String("a\nb\n".to_string())]),
+        ),
+    ]));
+
+    let config = PrettyConfig::new().string_style(StringStyle::Auto);
+    let glass = to_string_pretty(&value, config.clone()).unwrap();
+    assert_eq!(
+        glass,
+        r####"root {
+    path r"C:\temp\new",
+    quote r#"say "hi""#,
+    one "a\\b",
+    text """
+        first
+          indented
+
+        end \""" \\\x0d
+        """,
+    list [
+        """
+            a
+            b
+
+            """,
+    ],
+},"####
+    );
+    assert_eq!(from_str::<Value>(&glass).unwrap(), value);
+
+    let compact = to_string_pretty(&value, config.clone().compact(true)).unwrap();
+    assert!(compact.contains(r#"text "first\n  indented\n\nend \"\"\" \\\r""#));
+    assert_eq!(from_str::<Value>(&compact).unwrap(), value);
+
+    let value = glass!({ text "héllo\nwörld" });
+    let glass = to_string_pretty(&value, config).unwrap();
+    assert_eq!(
+        glass,
+        "root {\n    text \"\"\"\n        héllo\n        wörld\n        \"\"\",\n},"
+    );
+    assert_eq!(from_str::<Value>(&glass).unwrap(), value);
+}
+
 #[test]
 fn hex_escape_mixed_with_other_escapes() {
     let input = r#"
@@ -180,10 +578,10 @@ fn hex_escape_mixed_with_other_escapes() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![(
+    let expected = Value::Map(Map::from([(
         "mixed".into(),
-        Value::String("helloAworld\n".to_string())
-    )]);
+        Value::String("helloAworld\n".to_string()),
+    )]));
 
     assert_eq!(ast, expected);
 }
@@ -247,10 +645,10 @@ fn parses_empty_collections() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![
-        ("empty_map".into(), Value::Map(thin_vec![])),
+    let expected = Value::Map(Map::from([
+        ("empty_map".into(), Value::Map(Map::from([]))),
         ("empty_array".into(), Value::Array(thin_vec![])),
-    ]);
+    ]));
 
     assert_eq!(ast, expected);
 }
@@ -265,20 +663,429 @@ fn parses_mixed_array() {
 
     let ast: Value = from_str(input).unwrap();
 
-    let expected = Value::Map(thin_vec![(
+    let expected = Value::Map(Map::from([(
         "mixed".into(),
         Value::Array(thin_vec![
-            Value::Number(1.0),
+            Value::Number(1.into()),
             Value::String("two".to_string()),
             Value::Bool(true),
             Value::Array(thin_vec![]),
-            Value::Map(thin_vec![]),
+            Value::Map(Map::from([])),
+        ]),
+    )]));
+
+    assert_eq!(ast, expected);
+}
+
+#[test]
+fn lex_error_reports_position() {
+    let input = "root {\n    name \"glass\",\n    bad @,\n},";
+
+    let err = from_str::<Value>(input).unwrap_err();
+    let span = err.span().unwrap();
+
+    assert_eq!((span.line, span.column), (3, 9));
+    assert_eq!(&input[span.start..span.end], "@");
+    assert!(err.to_string().contains("3:9"));
+}
+
+#[test]
+fn parse_error_reports_position() {
+    let input = "root {\n    name \"glass\"\n    version \"0.1.0\",\n},";
+
+    let err = from_str::<Value>(input).unwrap_err();
+    let span = err.span().unwrap();
+
+    assert_eq!((span.line, span.column), (3, 5));
+    assert_eq!(&input[span.start..span.end], "version");
+}
+
+#[test]
+fn unexpected_eof_reports_position() {
+    let input = "root {\n    name \"glass\",\n";
+
+    let err = from_str::<Value>(input).unwrap_err();
+    let span = err.span().unwrap();
+
+    assert!(err.to_string().contains("found end of file"));
+    assert_eq!((span.line, span.column), (3, 1));
+    assert_eq!(span.start, input.len());
+}
+
+#[test]
+fn parse_errors_say_what_was_expected() {
+    let cases = [
+        (
+            "root {\n    name \"glass\"\n    version 1,\n},",
+            "expected `,` or `}` after map value, found key `version` at 3:5, in map started at 1:6",
+        ),
+        (
+            "root {\n    tags [\"a\" \"b\"],\n},",
+            "expected `,` or `]` after array element, found string at 2:15, in array started at 2:10",
+        ),
+        (
+            "root {\n    1 2,\n},",
+            "expected a key or `}`, found number `1` at 2:5, in map started at 1:6",
+        ),
+        ("root [1],", "expected `{` after root key, found `[` at 1:6"),
+        (
+            "root { a 1, } }",
+            "expected `,` or end of file after root map, found `}` at 1:15",
+        ),
+    ];
+
+    for (input, message) in cases {
+        let err = from_str::<Value>(input).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("parser error: {message}"),
+            "{input}"
+        );
+
+        let err = parse_value(input, &ParseOptions::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("parser error: {message}"),
+            "{input}"
+        );
+    }
+}
+
+#[test]
+fn recovering_reports_every_error() {
+    let input = "root {\n    a 1 2,\n    b @,\n    c [1, , 3],\n    d \"\\q\",\n    e true,\n},";
+
+    let (value, diagnostics) = parse_value_recovering(input, &ParseOptions::new());
+
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.span.line, d.span.column, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (
+                2,
+                9,
+                "expected `,` or `}` after map value, found number `2`, in map started at 1:6"
+            ),
+            (3, 7, "unexpected character: `@`"),
+            (
+                4,
+                11,
+                "expected a value or `]`, found `,`, in array started at 4:7"
+            ),
+            (5, 8, "invalid escape sequence"),
+        ]
+    );
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+    assert_eq!(diagnostics[0].expected, ["`,`", "`}`"]);
+    assert_eq!(diagnostics[2].expected, ["a value", "`]`"]);
+
+    let Value::Map(entries) = value else {
+        panic!("expected a map");
+    };
+    let keys: Vec<_> = entries.keys().collect();
+    assert_eq!(keys, ["a", "b", "c", "d", "e"]);
+    assert_eq!(*entries.get_index(1).unwrap().1, Value::Invalid);
+    assert_eq!(
+        *entries.get_index(2).unwrap().1,
+        Value::Array(thin_vec![
+            Value::Number(1.into()),
+            Value::Invalid,
+            Value::Number(3.into()),
         ])
-    )]);
+    );
+    assert_eq!(*entries.get_index(4).unwrap().1, Value::Bool(true));
+}
+
+#[test]
+fn recovering_reports_unclosed_input_once() {
+    let (value, diagnostics) =
+        parse_value_recovering("root {\n    a [1, {b 2,", &ParseOptions::new());
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "expected a key or `}`, found end of file, in map started at 2:11"
+    );
+    assert!(matches!(value, Value::Map(_)));
+
+    let (value, diagnostics) = parse_value_recovering("root { a \"open", &ParseOptions::new());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "unclosed string");
+    assert_eq!(value, Value::Map(Map::from([("a".into(), Value::Invalid)])));
+}
+
+#[test]
+fn recovering_agrees_with_strict_parsing() {
+    let input = "root {\n    name \"glass\",\n    tags [\"a\", \"b\"],\n},";
+
+    let (value, diagnostics) = parse_value_recovering(input, &ParseOptions::new());
+    assert!(diagnostics.is_empty());
+    assert_eq!(value, parse_value(input, &ParseOptions::new()).unwrap());
+    assert!(to_string(&Value::Invalid).is_err());
+}
+
+#[test]
+fn rejects_duplicate_keys_by_default() {
+    let input = "root {\n    name \"a\",\n    port 80,\n    name \"b\",\n},";
+    let error = from_str::<HashMap<String, Value>>(input).unwrap_err();
+    let Error::ParseError(ParseError::DuplicateKey(key, duplicate, first)) = &error else {
+        panic!("expected a duplicate key error, got {error:?}");
+    };
+    assert_eq!(&**key, "name");
+    assert_eq!((duplicate.line, duplicate.column), (4, 5));
+    assert_eq!((first.line, first.column), (2, 5));
+    assert_eq!(
+        error.to_string(),
+        "parser error: duplicate key `name` at 4:5, first defined at 2:5"
+    );
+    assert_eq!(parse_value(input, &ParseOptions::new()).unwrap_err(), error);
+    assert!(Document::parse(input).is_err());
+
+    // Only keys of the same map clash.
+    let input = "root { a { x 1 }, b { x 2, a 3 } }";
+    assert!(from_str::<Value>(input).is_ok());
+
+    let (value, diagnostics) =
+        parse_value_recovering("root { a 1, a 2, b 3, }", &ParseOptions::new());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "duplicate key `a`, first defined at 1:8"
+    );
+    assert_eq!(value, glass!({ a 1, b 3 }));
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
+
+#[test]
+fn duplicate_key_policies() {
+    let input = "root {\n    a { x 1 },\n    b 2,\n    // again\n    a { y [1, 2], y 3, z 4 },\n    c \"c\",\n    a 5,\n},";
+    let cases = [
+        (DuplicateKeys::LastWins, glass!({ b 2, c "c", a 5 })),
+        (DuplicateKeys::FirstWins, glass!({ a { x 1 }, b 2, c "c" })),
+        (
+            DuplicateKeys::Collect,
+            glass!({ a [{ x 1 }, { y [[1, 2], 3], z 4 }, 5], b 2, c "c" }),
+        ),
+    ];
+    for (policy, expected) in cases {
+        let options = ParseOptions::new().duplicate_keys(policy);
+        assert_eq!(
+            parse_value(input, &options).unwrap(),
+            expected,
+            "{policy:?}"
+        );
+        let value: Value = from_str_with_options(input, &options).unwrap();
+        assert_eq!(value, expected, "{policy:?}");
+    }
+
+    // The last of many repeated keys decides where each entry goes.
+    let input: String = (0..1000).map(|i| format!("k{} {i},\n", i % 7)).collect();
+    let options = ParseOptions::new()
+        .root(Root::Entries)
+        .duplicate_keys(DuplicateKeys::LastWins);
+    let expected: Map = (993..1000)
+        .map(|i| (format!("k{}", i % 7).into(), Value::Number(i.into())))
+        .collect();
+    assert_eq!(
+        parse_value(&input, &options).unwrap(),
+        Value::Map(expected.clone())
+    );
+    let value: Value = from_str_with_options(&input, &options).unwrap();
+    assert_eq!(value, Value::Map(expected));
+
+    // Maps nested in repeated values, in arrays and in collected values have their own
+    // duplicates.
+    let input = "root { a { b { c 1, c 2 }, b [{ d 1, d 2 }] }, a { e { f 1, f 2 } } }";
+    let cases = [
+        (DuplicateKeys::LastWins, glass!({ a { e { f 2 } } })),
+        (
+            DuplicateKeys::Collect,
+            glass!({ a [{ b [{ c [1, 2] }, [{ d [1, 2] }]] }, { e { f [1, 2] } }] }),
+        ),
+    ];
+    for (policy, expected) in cases {
+        let options = ParseOptions::new().duplicate_keys(policy);
+        assert_eq!(
+            parse_value(input, &options).unwrap(),
+            expected,
+            "{policy:?}"
+        );
+        let value: Value = from_str_with_options(input, &options).unwrap();
+        assert_eq!(value, expected, "{policy:?}");
+    }
+
+    // Dropping a value is allowed, but worth a warning when linting.
+    for (policy, outcome) in [
+        (DuplicateKeys::FirstWins, "is ignored"),
+        (DuplicateKeys::LastWins, "replaces it"),
+    ] {
+        let options = ParseOptions::new().duplicate_keys(policy);
+        let (value, diagnostics) = parse_value_recovering("root { a 1, b 2, a 3 }", &options);
+        assert_eq!(
+            value,
+            parse_value("root { a 1, b 2, a 3 }", &options).unwrap()
+        );
+        assert_eq!(diagnostics.len(), 1, "{policy:?}");
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].span.start, 17);
+        assert_eq!(
+            diagnostics[0].message,
+            format!("duplicate key `a` {outcome}, first defined at 1:8")
+        );
+    }
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Collect);
+    assert!(
+        parse_value_recovering("root { a 1, a 2 }", &options)
+            .1
+            .is_empty()
+    );
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Tags<'a> {
+        #[serde(borrow)]
+        tag: Vec<&'a str>,
+        name: &'a str,
+    }
+    let input = "tag \"a\",\nname \"n\",\ntag \"b\",\n";
+    let options = ParseOptions::new()
+        .root(Root::Entries)
+        .duplicate_keys(DuplicateKeys::Collect);
+    assert_eq!(
+        from_str_with_options::<Tags>(input, &options).unwrap(),
+        Tags {
+            tag: vec!["a", "b"],
+            name: "n",
+        }
+    );
+    let options = options.duplicate_keys(DuplicateKeys::LastWins);
+    assert_eq!(
+        from_str_with_options::<HashMap<&str, &str>>(input, &options).unwrap(),
+        HashMap::from([("tag", "b"), ("name", "n")])
+    );
+
+    // Errors in skipped values are still reported.
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::FirstWins);
+    assert!(from_str_with_options::<Value>("root { a 1, a [1 2] }", &options).is_err());
+}
+
+#[test]
+fn skips_comments() {
+    let input = r#"
+        // leading line comment
+        root {
+            /* block
+               comment */
+            name "glass", // trailing
+            list [1, /* inline */ 2,],
+        },
+    "#;
+
+    let ast: Value = from_str(input).unwrap();
+
+    let expected = Value::Map(Map::from([
+        ("name".into(), Value::String("glass".to_string())),
+        (
+            "list".into(),
+            Value::Array(thin_vec![Value::Number(1.into()), Value::Number(2.into())]),
+        ),
+    ]));
+
+    assert_eq!(ast, expected);
+}
+
+#[test]
+fn unclosed_block_comment() {
+    let input = "root { /* never closed },";
+
+    let result: Result<Value, _> = from_str(input);
+    assert!(result.is_err());
+}
+
+#[test]
+fn preserves_comments() {
+    let input = r#"
+        root {
+            // the name
+            name "glass", // inline
+            authors [
+                "septech",
+                // closing
+            ],
+        },
+    "#;
+
+    let options = ParseOptions::new().preserve_comments(true);
+    let ast = parse_value(input, &options).unwrap();
+
+    let expected = Value::Map(Map::from([
+        (
+            "name".into(),
+            Value::Commented(Box::new(Commented {
+                leading: thin_vec![Comment::line(" the name")],
+                value: Value::String("glass".to_string()),
+                trailing: thin_vec![Comment::line(" inline")],
+            })),
+        ),
+        (
+            "authors".into(),
+            Value::Array(thin_vec![Value::Commented(Box::new(Commented {
+                leading: thin_vec![],
+                value: Value::String("septech".to_string()),
+                trailing: thin_vec![Comment::line(" closing")],
+            }))]),
+        ),
+    ]));
 
     assert_eq!(ast, expected);
 }
 
+#[test]
+fn comments_roundtrip() {
+    let input = r#"
+        root {
+            // the name
+            name "glass",
+            /* block */ version "0.1.0", // inline
+            authors [
+                // first author
+                "septech",
+            ],
+        },
+    "#;
+
+    let options = ParseOptions::new().preserve_comments(true);
+    let ast = parse_value(input, &options).unwrap();
+
+    let serialized = to_string(&ast).unwrap();
+    assert!(serialized.contains("    // the name\n    name \"glass\",\n"));
+    assert!(serialized.contains("    /* block */\n    version \"0.1.0\", // inline\n"));
+    assert!(serialized.contains("        // first author\n        \"septech\",\n"));
+
+    assert_eq!(parse_value(&serialized, &options).unwrap(), ast);
+}
+
+#[test]
+fn commented_values_deserialize_transparently() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+    }
+
+    let options = ParseOptions::new().preserve_comments(true);
+    let ast = parse_value("root { // hi\n name \"glass\", },", &options).unwrap();
+
+    let config = Config::deserialize(ast).unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "glass".to_string()
+        }
+    );
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 struct Person {
     name: String,
@@ -306,56 +1113,1172 @@ fn test_serde_roundtrip() {
 
 #[test]
 fn test_value_roundtrip() {
-    let value = Value::Map(thin_vec![(
+    let value = Value::Map(Map::from([(
         "test".into(),
         Value::Array(thin_vec![
             Value::String("hello".to_string()),
-            Value::Number(42.0),
-        ])
-    )]);
+            Value::Number(42.0.into()),
+        ]),
+    )]));
 
-    let wrapped = Value::Map(thin_vec![("root".into(), value.clone())]);
-
-    let serialized = to_string(&wrapped).unwrap();
+    let serialized = to_string(&value).unwrap();
 
     let deserialized: Value = from_str(&serialized).unwrap();
     assert_eq!(value, deserialized);
 }
 
 #[test]
-fn test_simple_struct() {
-    #[derive(Debug, Serialize, Deserialize, PartialEq)]
-    struct Config {
-        name: String,
-        version: String,
-    }
-
-    let config = Config {
-        name: "myapp".to_string(),
-        version: "1.0.0".to_string(),
-    };
+fn value_accessors() {
+    let input = r#"root {
+        name "glass",
+        port 8080,
+        ratio 0.5,
+        enabled true,
+        nothing null,
+        data b"\x01",
+        tags ["a", "b"],
+        // the server
+        server { host "localhost" },
+    }"#;
+    let options = ParseOptions::default().preserve_comments(true);
+    let mut value = parse_value(input, &options).unwrap();
+
+    assert!(value.is_map());
+    assert_eq!(value.get("name").and_then(Value::as_str), Some("glass"));
+    assert_eq!(value["port"].as_u64(), Some(8080));
+    assert_eq!(value["port"].as_f64(), Some(8080.0));
+    assert!(value["port"].is_i64() && !value["port"].is_f64());
+    assert_eq!(value["ratio"].as_f64(), Some(0.5));
+    assert_eq!(value["ratio"].as_i64(), None);
+    assert_eq!(value["enabled"].as_bool(), Some(true));
+    assert!(value["nothing"].is_null());
+    assert_eq!(value["data"].as_bytes(), Some(&[1][..]));
+    assert_eq!(value["tags"][1].as_str(), Some("b"));
+    assert_eq!(value["tags"].as_array().map(|tags| tags.len()), Some(2));
+    // Comments are looked through.
+    assert_eq!(value["server"]["host"].as_str(), Some("localhost"));
+    assert!(value.get("missing").is_none());
+    assert!(value["missing"]["deeper"][3].is_null());
+    assert_eq!(value["name"].as_bool(), None);
+
+    *value.get_mut("port").unwrap() = 9090.into();
+    value["server"]["host"]
+        .as_string_mut()
+        .unwrap()
+        .push_str(":1");
+    value["tags"].as_array_mut().unwrap().push("c".into());
+    value["tags"][0] = Value::Null;
+    value["added"]["nested"] = true.into();
+    assert_eq!(value["port"], 9090.into());
+    assert_eq!(value["server"]["host"], "localhost:1".into());
+    assert_eq!(value["tags"], Value::from(vec![None, Some("b"), Some("c")]));
+    assert_eq!(value["added"], Value::from_iter([("nested", true)]));
+    assert!(matches!(
+        *value.as_map().unwrap().get_index(7).unwrap().1,
+        Value::Commented(_)
+    ));
+}
 
-    let serialized = to_string(&config).unwrap();
-    let deserialized: Config = from_str(&serialized).unwrap();
-    assert_eq!(config, deserialized);
+#[test]
+#[should_panic(expected = "cannot index into a non-map value with `a`")]
+fn indexing_a_scalar_by_key_panics() {
+    let mut value = Value::from(1);
+    value["a"] = Value::Null;
 }
 
 #[test]
-fn test_nested_struct() {
-    #[derive(Debug, Serialize, Deserialize, PartialEq)]
-    struct Inner {
-        value: f64,
-    }
+fn value_from_rust_values() {
+    assert_eq!(Value::from(1u8), Value::Number(1.into()));
+    assert_eq!(Value::from(-1.5), Value::Number((-1.5).into()));
+    assert_eq!(Value::from("a"), Value::String("a".into()));
+    assert_eq!(Value::from(Cow::Borrowed("a")), Value::String("a".into()));
+    assert_eq!(Value::from(None::<bool>), Value::Null);
+    assert_eq!(Value::from(()), Value::Null);
+    assert_eq!(
+        Value::from(&[1, 2][..]),
+        Value::Array(thin_vec![1.into(), 2.into()])
+    );
+    assert_eq!(
+        Value::from(BTreeMap::from([("b", 2), ("a", 1)])),
+        Value::Map(Map::from([("a".into(), 1.into()), ("b".into(), 2.into())]))
+    );
+    let map = Value::from(HashMap::from([("a".to_string(), vec!["x"])]));
+    assert_eq!(map["a"][0], "x".into());
+}
 
-    #[derive(Debug, Serialize, Deserialize, PartialEq)]
-    struct Outer {
-        inner: Inner,
-        name: String,
+#[test]
+fn maps_keep_file_order() {
+    let input = format!(
+        "root {{ {} }}",
+        (0..1000)
+            .rev()
+            .map(|i| format!("k{i} {i},"))
+            .collect::<String>()
+    );
+    let value: Value = from_str(&input).unwrap();
+    let map = value.as_map().unwrap();
+    assert_eq!(map.len(), 1000);
+    assert_eq!(map.get_index(0), Some(("k999", &999.into())));
+    assert_eq!(map.get_index_of("k0"), Some(999));
+    assert_eq!(map["k500"], 500.into());
+    assert!(map.contains_key("k1") && !map.contains_key("k1000"));
+
+    let mut map = Map::new();
+    map.insert("b", 1.into());
+    map.insert("a", 2.into());
+    assert_eq!(map.insert("b", 3.into()), Some(1.into()));
+    map.insert_at(0, "c", 4.into());
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["c", "b", "a"]);
+    assert_eq!(map.remove("c"), Some(4.into()));
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "a"]);
+
+    *map.entry("a").or_insert(Value::Null) = 5.into();
+    map.entry("d").or_insert_with(|| 6.into());
+    map.entry("b")
+        .and_modify(|value| *value = 7.into())
+        .or_insert(Value::Null);
+    match map.entry("d") {
+        map::Entry::Occupied(entry) => assert_eq!(entry.remove(), 6.into()),
+        map::Entry::Vacant(_) => panic!("`d` was inserted"),
     }
+    let entries: Vec<_> = map.iter().collect();
+    assert_eq!(entries, [("b", &7.into()), ("a", &5.into())]);
+
+    // Equality takes the order into account.
+    let reversed: Map = map.clone().into_iter().rev().collect();
+    assert_ne!(map, reversed);
+    assert_eq!(
+        map,
+        Map::from([("b".into(), 7.into()), ("a".into(), 5.into())])
+    );
+    assert_eq!(
+        format!("{map:?}"),
+        r#"{"b": Number(Number(7)), "a": Number(Number(5))}"#
+    );
+}
 
-    let outer = Outer {
-        inner: Inner { value: 42.0 },
-        name: "test".to_string(),
+#[test]
+fn glass_macro() {
+    let port = 8080;
+    let value = glass! {
+        name "glass",
+        "dashed-key" null,
+        server { port port, hosts ["a", "b",], },
+        matrix [[1, 2], [], [null, { x -1 }]],
+        (format!("key{}", port % 10)) -1.5,
+        len "abc".len(),
+    };
+    let expected = Value::Map(Map::from([
+        ("name".into(), "glass".into()),
+        ("dashed-key".into(), Value::Null),
+        (
+            "server".into(),
+            Value::Map(Map::from([
+                ("port".into(), 8080.into()),
+                ("hosts".into(), Value::from(["a", "b"])),
+            ])),
+        ),
+        (
+            "matrix".into(),
+            Value::Array(thin_vec![
+                Value::from([1, 2]),
+                Value::Array(ThinVec::new()),
+                Value::Array(thin_vec![
+                    Value::Null,
+                    Value::Map(Map::from([("x".into(), (-1).into())])),
+                ]),
+            ]),
+        ),
+        ("key0".into(), (-1.5).into()),
+        ("len".into(), 3u64.into()),
+    ]));
+    assert_eq!(value, expected);
+
+    assert_eq!(glass!(), Value::Map(Map::new()));
+    assert_eq!(glass!({}), Value::Map(Map::new()));
+    assert_eq!(glass!([]), Value::Array(ThinVec::new()));
+    assert_eq!(glass!(null), Value::Null);
+    assert_eq!(glass!(-1), (-1).into());
+    assert_eq!(glass!(port), 8080.into());
+    assert_eq!(glass!(("abc".len())), 3u64.into());
+    assert_eq!(
+        glass!([true, "x", [null]]),
+        Value::from_iter([
+            true.into(),
+            "x".into(),
+            Value::Array(thin_vec![Value::Null]),
+        ])
+    );
+}
+
+const SERVICES: &str = r#"root {
+    name "glass",
+    dependencies { thiserror "2.0.18", "serde-json" "1" },
+    servers [
+        { host "alpha", port 80, tls { enabled false } },
+        // the main one
+        { host "beta", port 8080, tls { enabled true } },
+        { host "gamma", port 8443.5, weight 2 },
+    ],
+    ports [22, 80, 443],
+    "a/b" { "~" 1 },
+},"#;
+
+#[test]
+fn looks_up_values_by_path() {
+    let options = ParseOptions::default().preserve_comments(true);
+    let mut value = parse_value(SERVICES, &options).unwrap();
+
+    assert_eq!(value.pointer(""), Some(&value));
+    assert_eq!(
+        value.pointer("/dependencies/thiserror"),
+        Some(&"2.0.18".into())
+    );
+    assert_eq!(value.pointer("/servers/1/host"), Some(&"beta".into()));
+    assert_eq!(value.pointer("/a~1b/~0"), Some(&1.into()));
+    assert_eq!(value.pointer("/servers/01"), None);
+    assert_eq!(value.pointer("/servers/-"), None);
+    assert_eq!(value.pointer("servers"), None);
+    assert_eq!(value.pointer("/name/0"), None);
+
+    assert_eq!(value.get_path("servers[2].host"), Some(&"gamma".into()));
+    assert_eq!(
+        value.get_path(".servers[1].tls.enabled"),
+        Some(&true.into())
+    );
+    assert_eq!(
+        value.get_path("dependencies.\"serde-json\""),
+        Some(&"1".into())
+    );
+    assert_eq!(value.get_path("[\"a/b\"][\"~\"]"), Some(&1.into()));
+    assert_eq!(value.get_path(""), Some(&value));
+    assert_eq!(value.get_path("servers[3].host"), None);
+    assert_eq!(value.get_path("servers[*].host"), None);
+    assert_eq!(value.get_path("servers["), None);
+
+    *value.get_path_mut("servers[0].port").unwrap() = 81.into();
+    *value.pointer_mut("/ports/0").unwrap() = 2222.into();
+    assert_eq!(value["servers"][0]["port"], 81.into());
+    assert_eq!(value["ports"][0], 2222.into());
+    assert!(value.get_path_mut("servers[0].missing").is_none());
+}
+
+#[test]
+fn queries_values() {
+    let value = parse_value(SERVICES, &ParseOptions::default()).unwrap();
+    let query = |path| value.query(path).unwrap();
+
+    assert_eq!(
+        query("servers[*].host"),
+        [&"alpha".into(), &"beta".into(), &"gamma".into()]
+    );
+    assert_eq!(query("dependencies.*"), [&"2.0.18".into(), &"1".into()]);
+    assert_eq!(
+        query("servers[?port > 8000].host"),
+        [&"beta".into(), &"gamma".into()]
+    );
+    assert_eq!(query("servers[?port == 80.0].host"), [&"alpha".into()]);
+    assert_eq!(query("servers[?tls.enabled].host"), [&"beta".into()]);
+    assert_eq!(query("servers[?weight].port"), [&8443.5.into()]);
+    assert_eq!(
+        query("servers[?host != \"beta\"].port"),
+        [&80.into(), &8443.5.into()]
+    );
+    assert_eq!(
+        query("servers[?host >= \"beta\"].tls"),
+        [&glass!({ enabled true })]
+    );
+    assert_eq!(query("ports[?@ < 100]"), [&22.into(), &80.into()]);
+    assert_eq!(query("ports[?@ == \"22\"]"), Vec::<&Value>::new());
+    assert_eq!(query("servers[5]"), Vec::<&Value>::new());
+    assert_eq!(query("name[*]"), Vec::<&Value>::new());
+
+    assert_eq!(
+        value.query("servers[").unwrap_err(),
+        Error::Path(
+            "expected an index, a quoted key, `*` or `?`, found end of path at column 9 of \
+             `servers[`"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        value.query("servers[?port >]").unwrap_err(),
+        Error::Path(
+            "expected a string, number, `true`, `false` or `null`, found `]` at column 16 of \
+             `servers[?port >]`"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        value.query("a..b").unwrap_err(),
+        Error::Path("expected a key, found `.` at column 3 of `a..b`".to_owned())
+    );
+    assert_eq!(
+        value.query("servers[host]").unwrap_err(),
+        Error::Path(
+            "expected an index, a quoted key, `*` or `?`, found `h` at column 9 of \
+             `servers[host]`"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        value.query("a b").unwrap_err(),
+        Error::Path("expected `.` or `[`, found ` ` at column 2 of `a b`".to_owned())
+    );
+    assert_eq!(
+        value.query("[\"a").unwrap_err(),
+        Error::Path("unclosed string at column 2 of `[\"a`".to_owned())
+    );
+}
+
+#[test]
+fn queries_documents_without_building_them() {
+    for path in [
+        "servers[*].host",
+        "servers[1]",
+        "servers[?port > 8000].host",
+        "servers[?tls.enabled == false]",
+        "dependencies.*",
+        "ports[?@ >= 80]",
+        "\"a/b\"",
+        "",
+    ] {
+        let value: Value = from_str(SERVICES).unwrap();
+        let expected: Vec<Value> = value.query(path).unwrap().into_iter().cloned().collect();
+        assert_eq!(glass::query(SERVICES, path).unwrap(), expected, "{path}");
+    }
+
+    let entries = "name \"glass\",\nports [1, 2],";
+    let options = ParseOptions::default().root(Root::Entries);
+    assert_eq!(
+        glass::query_with_options(entries, "ports[1]", &options).unwrap(),
+        [Value::from(2)]
+    );
+    assert!(matches!(
+        glass::query("root { a [1, }", "b"),
+        Err(Error::ParseError(_))
+    ));
+    assert!(matches!(glass::query("root {}", "a["), Err(Error::Path(_))));
+}
+
+const SERVICES_SCHEMA: &str = r#"schema {
+    type "map",
+    fields {
+        name { type "string", pattern "[a-z]*", min_length 1 },
+        dependencies { type "map", optional true, additional { type "string", pattern "[0-9]" } },
+        servers {
+            type "array",
+            min_items 1,
+            max_items 2,
+            items {
+                type "map",
+                fields {
+                    host { enum ["alpha", "beta"] },
+                    port { type "integer", min 1, max 8443 },
+                    tls { optional true, fields { enabled { type "bool" } } },
+                },
+            },
+        },
+        ports { type "array", items { type ["integer", "null"], max 1024 } },
+        mode { optional true, enum ["dev", "prod"] },
+    },
+    additional true,
+},"#;
+
+#[test]
+fn validates_values_against_schemas() {
+    let schema = Schema::parse(SERVICES_SCHEMA).unwrap();
+    let value = parse_value(SERVICES, &ParseOptions::default()).unwrap();
+
+    let violations: Vec<String> = schema
+        .validate(&value)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        violations,
+        [
+            "dependencies.thiserror: expected a string matching `[0-9]`, found \"2.0.18\"",
+            "servers: expected at most 2 elements, found 3",
+            "servers[2].host: expected one of \"alpha\", \"beta\", found \"gamma\"",
+            "servers[2].port: expected an integer, found a float",
+            "servers[2].weight: unexpected key",
+        ]
+    );
+    for violation in schema.validate(&value) {
+        assert!(value.get_path(&violation.path).is_some(), "{violation}");
+    }
+
+    let valid = glass!({
+        name "glass",
+        servers [{ host "beta", port 8443 }],
+        ports [22, null],
+        mode "dev",
+        extra [],
+    });
+    assert_eq!(schema.validate(&valid), []);
+
+    let invalid = glass!({
+        name "",
+        dependencies { "serde-json" 1 },
+        servers [{ port 0, tls {} }],
+        ports [2048, "22"],
+        mode "test",
+    });
+    let violations: Vec<String> = schema
+        .validate(&invalid)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        violations,
+        [
+            "name: expected a string matching `[a-z]*`, found \"\"",
+            "name: expected at least 1 character, found 0",
+            "dependencies[\"serde-json\"]: expected a string, found an integer",
+            "servers[0]: missing required key `host`",
+            "servers[0].port: expected at least 1, found 0",
+            "servers[0].tls: missing required key `enabled`",
+            "ports[0]: expected at most 1024, found 2048",
+            "ports[1]: expected an integer or null, found a string",
+            "mode: expected one of \"dev\", \"prod\", found \"test\"",
+        ]
+    );
+    assert_eq!(
+        schema
+            .validate(&glass!({ name "a", servers [] }))
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            "missing required key `ports`",
+            "servers: expected at least 1 element, found 0",
+        ]
+    );
+    assert_eq!(
+        schema.validate(&glass!([1]))[0].to_string(),
+        "expected a map, found an array"
+    );
+}
+
+#[test]
+fn matches_string_patterns() {
+    let cases = [
+        ("*.example.com", "api.example.com", true),
+        ("*.example.com", "example.com", false),
+        ("v?.*", "v1.2", true),
+        ("v?.*", "v12.2", false),
+        ("[a-c]*[!0-9]", "build", true),
+        ("[a-c]*[!0-9]", "build2", false),
+        ("[]x]", "]", true),
+        ("a\\*", "a*", true),
+        ("a\\*", "ab", false),
+        ("*a*b", "xaxab", true),
+        ("", "", true),
+    ];
+    for (pattern, string, matches) in cases {
+        let schema = Schema::from_value(&glass!({ pattern(pattern) })).unwrap();
+        assert_eq!(
+            schema.validate(&string.into()).is_empty(),
+            matches,
+            "{pattern} {string}"
+        );
+    }
+}
+
+#[test]
+fn rejects_invalid_schemas() {
+    let cases = [
+        (
+            "root { type \"strng\" }",
+            "unknown type `strng`, expected one of any, null, bool, integer, number, string, bytes, array, map at `type`",
+        ),
+        (
+            "root { fields { port { min \"1\" } } }",
+            "expected a number at `fields.port.min`",
+        ),
+        (
+            "root { items { mni 1 } }",
+            "unknown schema keyword at `items.mni`",
+        ),
+        (
+            "root { type [\"map\", 1] }",
+            "expected a type name at `type[1]`",
+        ),
+        (
+            "root { min_items -1 }",
+            "expected a non-negative integer at `min_items`",
+        ),
+        (
+            "root { pattern \"[a-\" }",
+            "unclosed `[` in pattern at `pattern`",
+        ),
+        (
+            "root { enum [[1]] }",
+            "expected a string, number, bool or null at `enum[0]`",
+        ),
+        (
+            "root { items [] }",
+            "expected a map of schema keywords at `items`",
+        ),
+    ];
+    for (input, message) in cases {
+        assert_eq!(
+            Schema::parse(input).unwrap_err(),
+            Error::Schema(message.to_owned()),
+            "{input}"
+        );
+    }
+    assert!(matches!(Schema::parse("root {"), Err(Error::ParseError(_))));
+}
+
+#[cfg(feature = "schemars")]
+#[test]
+fn describes_config_types() {
+    use schemars::JsonSchema;
+
+    /// Where to deploy.
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Config {
+        /// Name of the service.
+        name: String,
+        /// Port to listen on.
+        #[serde(default = "default_port")]
+        port: u16,
+        tls: Option<Tls>,
+        mode: Mode,
+        hosts: Vec<String>,
+        env: HashMap<String, String>,
+        next: Option<Box<Config>>,
+    }
+
+    fn default_port() -> u16 {
+        8080
+    }
+
+    /// TLS settings.
+    ///
+    /// Off by default.
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Tls {
+        enabled: bool,
+        cert: Option<String>,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    enum Mode {
+        Dev,
+        Prod,
+        Canary { percent: u8 },
+    }
+
+    let described = glass::describe::<Config>();
+    assert_eq!(
+        described,
+        r#"// Where to deploy.
+schema {
+    type "map",
+    fields {
+        // Name of the service.
+        name {type "string"},
+        // Port to listen on.
+        port {type "integer", min 0, max 65535, default 8080, optional true},
+        // TLS settings.
+        //
+        // Off by default.
+        tls {
+            type ["map", "null"],
+            fields {enabled {type "bool"}, cert {type ["string", "null"], optional true}},
+            additional true,
+            optional true,
+        },
+        mode {
+            type ["string", "map"],
+            fields {
+                Canary {
+                    type "map",
+                    fields {percent {type "integer", min 0, max 255}},
+                    additional true,
+                    optional true,
+                },
+            },
+            additional false,
+        },
+        hosts {type "array", items {type "string"}},
+        env {type "map", additional {type "string"}},
+        // Where to deploy.
+        next {type ["map", "null"], optional true},
+    },
+    additional false,
+},"#
+    );
+
+    let schema = Schema::parse(&described).unwrap();
+    let config = "root {\n    name \"api\",\n    mode { Canary { percent 10 } },\n    hosts [\"a\"],\n    env {},\n},";
+    assert_eq!(
+        schema.validate(&parse_value(config, &ParseOptions::default()).unwrap()),
+        []
+    );
+    let config = "root {\n    name \"api\",\n    port 70000,\n    mode \"Dev\",\n    hosts [1],\n    extra 1,\n},";
+    let violations: Vec<String> = schema
+        .validate(&parse_value(config, &ParseOptions::default()).unwrap())
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        violations,
+        [
+            "missing required key `env`",
+            "port: expected at most 65535, found 70000",
+            "hosts[0]: expected a string, found an integer",
+            "extra: unexpected key",
+        ]
+    );
+}
+
+#[test]
+fn rejects_trailing_input() {
+    let input = "root { a 1, },\nroot { a 2, },";
+    let message = "parser error: expected end of file after root map, found key `root` at 2:1";
+    assert_eq!(from_str::<Value>(input).unwrap_err().to_string(), message);
+    assert_eq!(
+        parse_value(input, &ParseOptions::new())
+            .unwrap_err()
+            .to_string(),
+        message
+    );
+
+    let options = ParseOptions::new().root(Root::Bare);
+    let err = parse_value("[1], 2", &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parser error: expected end of file after root value, found number `2` at 1:6"
+    );
+
+    let input = "root { a 1, }, // done\n/* really */";
+    assert!(from_str::<Value>(input).is_ok());
+    assert!(parse_value(input, &ParseOptions::new()).is_ok());
+}
+
+#[test]
+fn reads_document_streams() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Record<'a> {
+        id: u32,
+        message: &'a str,
+    }
+
+    let input = "root { id 1, message \"start\", },\n// comment\nroot { id 2, message \"stop\", }";
+    let records = Deserializer::from_str(input)
+        .into_iter::<Record>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        records,
+        [
+            Record {
+                id: 1,
+                message: "start"
+            },
+            Record {
+                id: 2,
+                message: "stop"
+            },
+        ]
+    );
+
+    let options = ParseOptions::new().root(Root::Bare);
+    let numbers = Deserializer::with_options("1, 2, 3,", &options)
+        .into_iter::<u32>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(numbers, [1, 2, 3]);
+    let options = ParseOptions::new().duplicate_keys(DuplicateKeys::Collect);
+    let collected = Deserializer::with_options("root { a 1, a 2 },\nroot { b 3, b 4 },", &options)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(collected, [glass!({ a [1, 2] }), glass!({ b [3, 4] })]);
+    assert_eq!(
+        Deserializer::from_str("  // nothing\n")
+            .into_iter::<Value>()
+            .count(),
+        0
+    );
+
+    let input = "root { id 1, },\nroot { id \"two\", },\nroot { id 3, },";
+    let mut stream = Deserializer::from_str(input).into_iter::<BTreeMap<String, u32>>();
+    assert_eq!(
+        stream.next().unwrap().unwrap(),
+        BTreeMap::from([("id".to_string(), 1)])
+    );
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+
+    let input = "root { a 1, } root { a 2, },";
+    let results = Deserializer::from_str(input)
+        .into_iter::<Value>()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].as_ref().unwrap_err().to_string(),
+        "parser error: expected `,` or end of file after root map, found key `root` at 1:15"
+    );
+}
+
+#[test]
+fn reads_document_streams_from_readers() {
+    let input = "root { id 1, },\nroot { id 2, },\nroot { id, },";
+    let reader = io::Cursor::new(input.as_bytes());
+    let mut stream =
+        ReaderStreamDeserializer::<_, BTreeMap<String, u32>>::new(reader, &ParseOptions::new());
+
+    for id in [1, 2] {
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            BTreeMap::from([("id".to_string(), id)])
+        );
+    }
+    let err = stream.next().unwrap().unwrap_err();
+    assert_eq!(err.span().unwrap().line, 3);
+    assert_eq!(err.span().unwrap().start, input.find("id,").unwrap() + 2);
+    assert!(stream.next().is_none());
+
+    let mut stream = ReaderStreamDeserializer::<_, Value>::new(&[0xff][..], &ParseOptions::new());
+    assert!(matches!(stream.next(), Some(Err(Error::Io(_)))));
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn reads_documents_as_they_arrive() {
+    /// Hands out one chunk per read, like a pipe that is still being written to.
+    struct Chunks<'a>(Rc<RefCell<VecDeque<&'a [u8]>>>);
+
+    impl io::Read for Chunks<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some(chunk) = self.0.borrow_mut().pop_front() else {
+                return Ok(0);
+            };
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    let input = "root { name \"café\" },\nroot { name \"b\" },\n// trailing\nroot { name 3 },";
+    let bytes = input.as_bytes();
+    // The second chunk ends inside `é`, the third right after the first document's comma.
+    let split = input.find('é').unwrap() + 1;
+    let comma = input.find(",\n").unwrap() + 1;
+    let chunks = Rc::new(RefCell::new(VecDeque::from([
+        &bytes[..8],
+        &bytes[8..split],
+        &bytes[split..comma],
+        &bytes[comma..30],
+        &bytes[30..],
+    ])));
+    let mut stream = ReaderStreamDeserializer::<_, BTreeMap<String, String>>::new(
+        Chunks(chunks.clone()),
+        &ParseOptions::new(),
+    );
+
+    assert_eq!(stream.next().unwrap().unwrap()["name"], "café");
+    assert_eq!(chunks.borrow().len(), 2);
+    assert_eq!(stream.next().unwrap().unwrap()["name"], "b");
+    assert!(chunks.borrow().is_empty());
+    assert!(matches!(stream.next(), Some(Err(Error::Serde(_)))));
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn documents_round_trip_byte_for_byte() {
+    let input = "// package manifest\nroot   {\n    name \"glass\" , // the name\n\n    \"odd key\" 0x1F,\n  tags [ \"a\",\"b\" ] ,\n    /* nested */ deps { serde   \"1\" }\n}  ,\n// end\n";
+    let document = Document::parse(input).unwrap();
+    assert_eq!(document.to_string(), input);
+    assert_eq!(
+        document.value(),
+        parse_value(input, &ParseOptions::new()).unwrap()
+    );
+    assert_eq!(
+        document.get(&["deps", "serde"]),
+        Some(Value::String("1".to_string()))
+    );
+    assert_eq!(
+        document.get(&["tags", "1"]),
+        Some(Value::String("b".to_string()))
+    );
+    assert_eq!(document.get(&["tags", "2"]), None);
+
+    let crlf = "root {\r\n    a 1, // one\r\n    b [\r\n        2,\r\n    ],\r\n},\r\n";
+    assert_eq!(Document::parse(crlf).unwrap().to_string(), crlf);
+
+    let entries = "// settings\nname \"glass\",\nport 80,";
+    let options = ParseOptions::new().root(Root::Entries);
+    let document = Document::parse_with_options(entries, &options).unwrap();
+    assert_eq!(document.to_string(), entries);
+
+    let err = Document::parse("root { a 1 b 2 },").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parser error: expected `,` or `}` after map value, found key `b` at 1:12, in map started at 1:6"
+    );
+}
+
+#[test]
+fn edits_documents_in_place() {
+    let input = "\
+// glass manifest
+root {
+    name \"glass\",
+    version \"0.1.0\", // bumped by CI
+    authors [\"septech\"],
+    dependencies {
+        // parsing
+        thiserror \"2.0.18\",
+    },
+},
+";
+    let mut document = Document::parse(input).unwrap();
+
+    document.set(&["version"], "0.2.0").unwrap();
+    document
+        .set(
+            &["dependencies", "serde"],
+            &BTreeMap::from([("version", "1")]),
+        )
+        .unwrap();
+    document.insert(&["authors", "-"], "agent").unwrap();
+    document.set(&["license"], "MIT").unwrap();
+    assert_eq!(
+        document.to_string(),
+        "\
+// glass manifest
+root {
+    name \"glass\",
+    version \"0.2.0\", // bumped by CI
+    authors [\"septech\", \"agent\"],
+    dependencies {
+        // parsing
+        thiserror \"2.0.18\",
+        serde {
+            version \"1\",
+        },
+    },
+    license \"MIT\",
+},
+"
+    );
+
+    assert!(document.remove(&["dependencies", "thiserror"]));
+    assert!(document.remove(&["authors", "0"]));
+    assert!(!document.remove(&["missing"]));
+    assert_eq!(
+        document.to_string(),
+        "\
+// glass manifest
+root {
+    name \"glass\",
+    version \"0.2.0\", // bumped by CI
+    authors [\"agent\"],
+    dependencies {
+        serde {
+            version \"1\",
+        },
+    },
+    license \"MIT\",
+},
+"
+    );
+    assert_eq!(
+        document.value(),
+        parse_value(&document.to_string(), &ParseOptions::new()).unwrap()
+    );
+}
+
+#[test]
+fn lays_out_new_entries_like_their_neighbors() {
+    let mut document =
+        Document::parse("root {\n\ta 1,\n\tb {},\n\tc [1, 2, 3],\n\td [],\n},").unwrap();
+    document.insert(&["b", "x"], &true).unwrap();
+    document.insert(&["c", "0"], &0).unwrap();
+    document.insert(&["d", "0"], "first").unwrap();
+    document.insert(&["a2"], &[1, 2]).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "root {\n\ta 1,\n\tb {\n\t\tx true,\n\t},\n\tc [0, 1, 2, 3],\n\td [\n\t\t\"first\",\n\t],\n\ta2 [\n\t\t1,\n\t\t2,\n\t],\n},"
+    );
+
+    let mut document = Document::parse("root {\n    a 1,\n    // about b\n    b 2\n}").unwrap();
+    document.insert(&["c"], &3).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "root {\n    a 1,\n    // about b\n    b 2,\n    c 3\n}"
+    );
+    assert!(document.remove(&["b"]));
+    assert_eq!(document.to_string(), "root {\n    a 1,\n    c 3\n}");
+    assert!(document.remove(&["c"]));
+    assert_eq!(document.to_string(), "root {\n    a 1\n}");
+    assert!(document.remove(&["a"]));
+    assert_eq!(document.to_string(), "root {\n}");
+
+    let mut document = Document::parse("root { a {x 1, y 2, z 3}, \"b c\" 2, },").unwrap();
+    assert!(document.remove(&["a", "y"]));
+    assert!(document.remove(&["a", "z"]));
+    document.insert(&["b c", "-"], &0).unwrap_err();
+    document.set(&["a", "k-1"], "v").unwrap();
+    assert_eq!(
+        document.to_string(),
+        "root { a {x 1, \"k-1\" \"v\"}, \"b c\" 2, },"
+    );
+
+    let options = ParseOptions::new().root(Root::Entries);
+    let mut document = Document::parse_with_options("// settings\n", &options).unwrap();
+    document.set(&["name"], "glass").unwrap();
+    document.set(&["port"], &80).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "// settings\nname \"glass\",\nport 80,\n"
+    );
+}
+
+#[test]
+fn document_edit_errors() {
+    let mut document = Document::parse("root { a 1, list [1], },").unwrap();
+    assert_eq!(
+        document.set(&["b", "c"], &1).unwrap_err().to_string(),
+        "path error: no value at `/b`"
+    );
+    assert_eq!(
+        document.set(&["list", "3"], &1).unwrap_err().to_string(),
+        "path error: no value at `/list/3`"
+    );
+    assert_eq!(
+        document.insert(&["a"], &2).unwrap_err().to_string(),
+        "path error: `/a` already exists"
+    );
+    assert_eq!(
+        document.set(&[], &[1]).unwrap_err().to_string(),
+        "path error: the document's value has to be a map"
+    );
+    assert_eq!(document.to_string(), "root { a 1, list [1], },");
+}
+
+#[test]
+fn custom_root_key() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Package {
+        name: String,
+        version: u32,
+    }
+
+    let package = Package {
+        name: "glass".to_string(),
+        version: 1,
+    };
+    let options = ParseOptions::new().root(Root::Key("package".into()));
+
+    let glass = to_string_pretty(
+        &package,
+        PrettyConfig::new().root(Root::Key("package".into())),
+    )
+    .unwrap();
+    assert_eq!(glass, "package {\n    name \"glass\",\n    version 1,\n},");
+    assert_eq!(
+        from_str_with_options::<Package>(&glass, &options).unwrap(),
+        package
+    );
+    assert_eq!(
+        parse_value(&glass, &options).unwrap(),
+        parse_value(&to_string(&package).unwrap(), &ParseOptions::new()).unwrap()
+    );
+
+    let err = from_str::<Package>(&glass).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parser error: invalid root, expected `root` at 1:1"
+    );
+    let err = parse_value("root { name \"glass\", },", &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parser error: invalid root, expected `package` at 1:1"
+    );
+
+    let options = ParseOptions::new().root(Root::AnyKey);
+    assert_eq!(
+        from_str_with_options::<Package>(&glass, &options).unwrap(),
+        package
+    );
+    let err = parse_value("[1],", &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parser error: expected a key, found `[` at 1:1"
+    );
+}
+
+#[test]
+fn top_level_entries() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        tags: Vec<String>,
+        limits: BTreeMap<String, u32>,
+    }
+
+    let config = Config {
+        name: "glass".to_string(),
+        tags: vec!["a".to_string()],
+        limits: BTreeMap::from([("depth".to_string(), 8)]),
+    };
+    let options = ParseOptions::new().root(Root::Entries);
+
+    let glass = to_string_pretty(&config, PrettyConfig::new().root(Root::Entries)).unwrap();
+    assert_eq!(
+        glass,
+        "name \"glass\",\ntags [\n    \"a\",\n],\nlimits {\n    depth 8,\n},"
+    );
+    assert_eq!(
+        from_str_with_options::<Config>(&glass, &options).unwrap(),
+        config
+    );
+
+    let value = parse_value(&glass, &options).unwrap();
+    assert_eq!(
+        value,
+        parse_value(&to_string(&config).unwrap(), &ParseOptions::new()).unwrap()
+    );
+
+    let compact = PrettyConfig::new()
+        .root(Root::Entries)
+        .compact(true)
+        .trailing_comma(TrailingComma::Multiline);
+    assert_eq!(
+        to_string_pretty(&value, compact).unwrap(),
+        "name \"glass\", tags [\"a\"], limits {depth 8}"
+    );
+
+    assert_eq!(parse_value("", &options).unwrap(), Value::Map(Map::new()));
+    assert_eq!(
+        from_str_with_options::<BTreeMap<String, u32>>("a 1, b 2", &options).unwrap(),
+        BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+    );
+
+    let expected =
+        "parser error: expected `,` or end of file after map value, found key `b` at 1:5";
+    let err = parse_value("a 1 b 2", &options).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+    let err = from_str_with_options::<Value>("a 1 b 2", &options).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+
+    let err = to_string_pretty(&[1, 2], PrettyConfig::new().root(Root::Entries)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "serde error: only a map or struct can be written as top-level entries"
+    );
+}
+
+#[test]
+fn bare_root_values() {
+    let options = ParseOptions::new().root(Root::Bare);
+
+    for (glass, value) in [
+        (
+            "{a 1, b [true]}",
+            Value::Map(Map::from([
+                ("a".into(), Value::Number(1.into())),
+                ("b".into(), Value::Array(thin_vec![Value::Bool(true)])),
+            ])),
+        ),
+        (
+            "[1, \"two\"],",
+            Value::Array(thin_vec![
+                Value::Number(1.into()),
+                Value::String("two".to_string()),
+            ]),
+        ),
+        ("\"text\"", Value::String("text".to_string())),
+        ("null", Value::Null),
+    ] {
+        assert_eq!(parse_value(glass, &options).unwrap(), value, "{glass}");
+        assert_eq!(
+            from_str_with_options::<Value>(glass, &options).unwrap(),
+            value,
+            "{glass}"
+        );
+    }
+
+    let config = PrettyConfig::new().root(Root::Bare);
+    assert_eq!(
+        to_string_pretty(&vec![1, 2], config.clone()).unwrap(),
+        "[\n    1,\n    2,\n]"
+    );
+    assert_eq!(
+        to_string_pretty(&"text", config.clone()).unwrap(),
+        "\"text\""
+    );
+
+    // Top-level values other than maps only round-trip without a root key.
+    let numbers = vec![1.0, 2.5];
+    let glass = to_string_pretty(&numbers, config.clone()).unwrap();
+    assert_eq!(
+        from_str_with_options::<Vec<f64>>(&glass, &options).unwrap(),
+        numbers
+    );
+    let glass = to_string_pretty(&42u8, config).unwrap();
+    assert_eq!(from_str_with_options::<u8>(&glass, &options).unwrap(), 42);
+    let expected = "serde error: only a map or struct can be written after a root key, other values need `Root::Bare`";
+    for root in [Root::default(), Root::AnyKey] {
+        let config = PrettyConfig::new().root(root);
+        let err = to_string_pretty(&numbers, config.clone()).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+        let err = to_string_pretty(&42u8, config).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+    assert_eq!(to_string(&"text").unwrap_err().to_string(), expected);
+
+    let err = parse_value("1 2", &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parser error: expected `,` or end of file after root value, found number `2` at 1:3"
+    );
+}
+
+#[test]
+fn test_simple_struct() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        version: String,
+    }
+
+    let config = Config {
+        name: "myapp".to_string(),
+        version: "1.0.0".to_string(),
+    };
+
+    let serialized = to_string(&config).unwrap();
+    let deserialized: Config = from_str(&serialized).unwrap();
+    assert_eq!(config, deserialized);
+}
+
+#[test]
+fn test_nested_struct() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Inner {
+        value: f64,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Outer {
+        inner: Inner,
+        name: String,
+    }
+
+    let outer = Outer {
+        inner: Inner { value: 42.0 },
+        name: "test".to_string(),
     };
 
     let serialized = to_string(&outer).unwrap();
@@ -363,18 +2286,421 @@ fn test_nested_struct() {
     assert_eq!(outer, deserialized);
 }
 
+#[test]
+fn test_options_and_units() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Marker;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Optional {
+        missing: Option<String>,
+        present: Option<f64>,
+        unit: (),
+        marker: Marker,
+        list: Vec<Option<bool>>,
+    }
+
+    let optional = Optional {
+        missing: None,
+        present: Some(1.5),
+        unit: (),
+        marker: Marker,
+        list: vec![Some(true), None],
+    };
+
+    let serialized = to_string(&optional).unwrap();
+    assert!(serialized.contains("missing null,"));
+    assert!(serialized.contains("unit null,"));
+    assert_eq!(from_str::<Optional>(&serialized).unwrap(), optional);
+
+    let value = parse_value(&serialized, &ParseOptions::new()).unwrap();
+    assert_eq!(Optional::deserialize(value).unwrap(), optional);
+}
+
+#[test]
+fn deserializes_borrowed_strings() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Package<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        description: Cow<'a, str>,
+        #[serde(borrow)]
+        escaped: Cow<'a, str>,
+        authors: Vec<&'a str>,
+    }
+
+    let input = r#"
+        root {
+            name "glass",
+            description "Glass parser",
+            escaped "line1\nline2",
+            authors ["septech",],
+        },
+    "#;
+
+    let package: Package = from_str(input).unwrap();
+
+    assert_eq!(package.name, "glass");
+    assert!(matches!(package.description, Cow::Borrowed("Glass parser")));
+    assert!(matches!(package.escaped, Cow::Owned(ref s) if s == "line1\nline2"));
+    assert_eq!(package.authors, vec!["septech"]);
+}
+
+#[test]
+fn borrowed_str_with_escapes_fails() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Borrowed<'a> {
+        name: &'a str,
+    }
+
+    let result: Result<Borrowed, _> = from_str(r#"root { name "a\tb", },"#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn deserializes_enums_and_skips_unknown_fields() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Kind {
+        Library,
+        Binary { name: String },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Target {
+        kind: Kind,
+        fallback: Kind,
+    }
+
+    let input = r#"
+        root {
+            kind { Binary { name "glass", }, },
+            ignored { nested [1, { deep true, },], },
+            fallback "Library",
+        },
+    "#;
+
+    let target: Target = from_str(input).unwrap();
+
+    assert_eq!(
+        target,
+        Target {
+            kind: Kind::Binary {
+                name: "glass".to_string()
+            },
+            fallback: Kind::Library,
+        }
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Point(f64, f64);
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Shape {
+    Empty,
+    Square(f64),
+    Circle { center: Point, r: f64 },
+    Line(Point, Point),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Drawing {
+    shapes: Vec<Shape>,
+}
+
+fn drawing() -> Drawing {
+    Drawing {
+        shapes: vec![
+            Shape::Empty,
+            Shape::Square(2.0),
+            Shape::Circle {
+                center: Point(0.0, 1.0),
+                r: 1.5,
+            },
+            Shape::Line(Point(0.0, 0.0), Point(3.0, 4.0)),
+        ],
+    }
+}
+
+#[test]
+fn enum_variants_round_trip() {
+    let glass = to_string_pretty(&drawing(), PrettyConfig::new().max_width(60)).unwrap();
+    assert_eq!(
+        glass,
+        "root {
+    shapes [
+        \"Empty\",
+        {Square 2.0,},
+        {Circle {center [0.0, 1.0,], r 1.5,},},
+        {Line [[0.0, 0.0,], [3.0, 4.0,],],},
+    ],
+},"
+    );
+
+    assert_eq!(from_str::<Drawing>(&glass).unwrap(), drawing());
+    let glass = to_string(&drawing()).unwrap();
+    assert_eq!(from_str::<Drawing>(&glass).unwrap(), drawing());
+    let value = parse_value(&glass, &ParseOptions::new()).unwrap();
+    assert_eq!(Drawing::deserialize(value).unwrap(), drawing());
+}
+
+#[test]
+fn quotes_keys_that_are_not_identifiers() {
+    let deps: BTreeMap<String, u32> = [
+        ("serde-json", 1),
+        ("a b", 2),
+        ("true", 3),
+        ("1x", 4),
+        ("", 5),
+        ("with\"quote", 6),
+        ("plain_key", 7),
+        ("ключ", 8),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_owned(), value))
+    .collect();
+
+    let glass = to_string(&deps).unwrap();
+    assert!(glass.contains("    \"serde-json\" 1,\n"));
+    assert!(glass.contains("    \"true\" 3,\n"));
+    assert!(glass.contains("    \"with\\\"quote\" 6,\n"));
+    assert!(glass.contains("    plain_key 7,\n"));
+    assert!(glass.contains("    ключ 8,\n"));
+
+    assert_eq!(from_str::<BTreeMap<String, u32>>(&glass).unwrap(), deps);
+    let value = parse_value(&glass, &ParseOptions::new()).unwrap();
+    assert_eq!(to_string(&value).unwrap(), glass);
+}
+
+#[test]
+fn non_string_map_keys_round_trip() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Keys {
+        ports: HashMap<u16, String>,
+        offsets: BTreeMap<i64, u8>,
+        flags: BTreeMap<bool, u8>,
+        letters: BTreeMap<char, u8>,
+        colors: BTreeMap<Color, u8>,
+    }
+
+    let keys = Keys {
+        ports: HashMap::from([(80, "http".to_owned())]),
+        offsets: BTreeMap::from([(-1, 1), (2, 2)]),
+        flags: BTreeMap::from([(false, 0), (true, 1)]),
+        letters: BTreeMap::from([('a', 1), ('-', 2)]),
+        colors: BTreeMap::from([(Color::Red, 1), (Color::Green, 2)]),
+    };
+
+    let glass = to_string(&keys).unwrap();
+    assert!(glass.contains("\"80\" \"http\""));
+    assert!(glass.contains("\"true\" 1"));
+    assert!(glass.contains("Red 1"));
+
+    assert_eq!(from_str::<Keys>(&glass).unwrap(), keys);
+    let value = parse_value(&glass, &ParseOptions::new()).unwrap();
+    assert_eq!(Keys::deserialize(value).unwrap(), keys);
+
+    let err = from_str::<HashMap<u16, u8>>("root { \"port\" 1, },").unwrap_err();
+    assert!(err.to_string().contains("expected u16"), "{err}");
+}
+
+#[test]
+fn parses_quoted_keys() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Package {
+        #[serde(rename = "package-name")]
+        name: String,
+        version: String,
+    }
+
+    let input = "root { \"package-name\" \"glass\", \"version\" \"0.1.0\", },";
+    let package: Package = from_str(input).unwrap();
+    assert_eq!(package.name, "glass");
+    assert_eq!(package.version, "0.1.0");
+
+    let value = parse_value(input, &ParseOptions::new()).unwrap();
+    let Value::Map(entries) = value else {
+        panic!("expected a map");
+    };
+    assert_eq!(entries.get_index(0).unwrap().0, "package-name");
+}
+
+#[test]
+fn byte_strings_round_trip() {
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Blob<'a> {
+        hash: ByteBuf,
+        #[serde(borrow)]
+        magic: &'a Bytes,
+    }
+
+    let input = r#"root { hash b"\x00\xffa\"\n", magic b"PNG", },"#;
+    let blob: Blob = from_str(input).unwrap();
+    assert_eq!(blob.hash.as_slice(), b"\x00\xffa\"\n");
+    assert_eq!(blob.magic.as_ref(), b"PNG");
+
+    let glass = to_string(&blob).unwrap();
+    assert_eq!(
+        glass,
+        "root {\n    hash b\"\\x00\\xffa\\\"\\n\",\n    magic b\"PNG\",\n},"
+    );
+    assert_eq!(from_str::<Blob>(&glass).unwrap(), blob);
+
+    let value = parse_value(input, &ParseOptions::new()).unwrap();
+    let Value::Map(entries) = &value else {
+        panic!("expected a map");
+    };
+    assert_eq!(
+        *entries.get_index(0).unwrap().1,
+        Value::Bytes(b"\x00\xffa\"\n".to_vec())
+    );
+    assert_eq!(to_string(&value).unwrap(), glass);
+
+    let err = from_str::<Value>(r#"root { b b"\q", },"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "lexer error: invalid escape sequence at 1:12"
+    );
+}
+
+#[test]
+fn writes_to_io_and_fmt_sinks() {
+    let person = Person {
+        name: "Alice".to_string(),
+        age: 30.0,
+        hobbies: vec!["reading".to_string()],
+    };
+    let expected = to_string(&person).unwrap();
+
+    let mut bytes = Vec::new();
+    to_writer(io::BufWriter::new(&mut bytes), &person).unwrap();
+    assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+
+    let mut text = String::from("// header\n");
+    to_fmt_writer(&mut text, &person).unwrap();
+    assert_eq!(text, format!("// header\n{expected}"));
+}
+
+#[test]
+fn reports_io_errors() {
+    struct Broken;
+
+    impl io::Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let err = to_writer(Broken, &BTreeMap::from([("a", 1)])).unwrap_err();
+    assert_eq!(err, Error::Io("disk on fire".to_string()));
+}
+
+#[test]
+fn serializer_writes_bare_values() {
+    let mut serializer = Serializer::new(String::new());
+    vec!["a", "b"].serialize(&mut serializer).unwrap();
+
+    assert_eq!(serializer.into_inner(), "[\n    \"a\",\n    \"b\",\n]");
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Ports {
+    name: String,
+    ports: Vec<u32>,
+    ranges: Vec<Vec<u32>>,
+}
+
+fn ports() -> Ports {
+    Ports {
+        name: "web".to_string(),
+        ports: vec![80, 443],
+        ranges: vec![(8000..8004).collect(), (9000..9020).collect()],
+    }
+}
+
+#[test]
+fn pretty_compact() {
+    let config = PrettyConfig::new()
+        .compact(true)
+        .trailing_comma(TrailingComma::Multiline);
+    let glass = to_string_pretty(&ports(), config).unwrap();
+
+    assert!(!glass.contains('\n'));
+    assert!(glass.starts_with("root {name \"web\", ports [80, 443], ranges [[8000, 8001,"));
+    assert_eq!(from_str::<Ports>(&glass).unwrap(), ports());
+}
+
+#[test]
+fn pretty_collapses_short_containers() {
+    let config = PrettyConfig::new()
+        .max_width(40)
+        .trailing_comma(TrailingComma::Multiline);
+    let glass = to_string_pretty(&ports(), config).unwrap();
+
+    assert!(glass.contains("\n    ports [80, 443],\n"));
+    assert!(glass.contains("\n        [8000, 8001, 8002, 8003],\n"));
+    assert!(glass.contains("\n        [\n            9000,\n"));
+    assert_eq!(from_str::<Ports>(&glass).unwrap(), ports());
+}
+
+#[test]
+fn pretty_tabs_crlf_without_trailing_commas() {
+    let config = PrettyConfig::new()
+        .tabs(true)
+        .newline(NewlineStyle::CrLf)
+        .trailing_comma(TrailingComma::Never);
+    let glass = to_string_pretty(&ports(), config).unwrap();
+    assert!(glass.starts_with("root {\r\n\tname \"web\",\r\n\tports [\r\n\t\t80,\r\n"));
+    assert!(glass.ends_with("\t\t]\r\n\t]\r\n}"));
+
+    let value = parse_value(&glass, &ParseOptions::new()).unwrap();
+    assert_eq!(from_str::<Ports>(&glass).unwrap(), ports());
+    assert_eq!(to_string(&value).unwrap(), to_string(&ports()).unwrap());
+}
+
+#[test]
+fn pretty_keeps_comments() {
+    let glass = "root {\n    // ports\n    ports [80, 443], // http\n},";
+    let value = parse_value(glass, &ParseOptions::new().preserve_comments(true)).unwrap();
+
+    let collapsed = to_string_pretty(&value, PrettyConfig::new().max_width(80)).unwrap();
+    assert_eq!(
+        collapsed,
+        "root {\n    // ports\n    ports [80, 443,], // http\n},"
+    );
+
+    let compact = to_string_pretty(&value, PrettyConfig::new().compact(true)).unwrap();
+    assert_eq!(compact, "root {/* ports*/ ports [80, 443,], /* http*/},");
+}
+
 // Property Testing
-fn identifier_strategy() -> impl Strategy<Value = String> {
-    "[a-zA-Z_][a-zA-Z0-9_]*"
+fn key_strategy() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-zA-Z_][a-zA-Z0-9_]*", any::<String>()]
 }
 
 fn value_strategy() -> impl Strategy<Value = Value> {
     let leaf = prop_oneof![
         any::<String>().prop_map(Value::String),
+        "(.|\n){0,20}".prop_map(Value::String),
+        any::<Vec<u8>>().prop_map(Value::Bytes),
         any::<f64>()
-            .prop_filter("non-nan-non-infinite", |n| !n.is_nan() && n.is_finite())
-            .prop_map(Value::Number),
+            .prop_filter("non-nan", |n| !n.is_nan())
+            .prop_map(|n| Value::Number(n.into())),
+        any::<i64>().prop_map(|n| Value::Number(n.into())),
+        any::<u64>().prop_map(|n| Value::Number(n.into())),
         any::<bool>().prop_map(Value::Bool),
+        Just(Value::Null),
     ];
 
     leaf.prop_recursive(
@@ -385,8 +2711,8 @@ fn value_strategy() -> impl Strategy<Value = Value> {
             prop_oneof![
                 prop::collection::vec(inner.clone(), 0..5)
                     .prop_map(|v| Value::Array(ThinVec::from(v))),
-                prop::collection::vec((identifier_strategy().prop_map(Into::into), inner), 0..5)
-                    .prop_map(|v| Value::Map(ThinVec::from(v))),
+                prop::collection::vec((key_strategy().prop_map(Into::into), inner), 0..5)
+                    .prop_map(|v| Value::Map(v.into_iter().collect())),
             ]
         },
     )
@@ -394,17 +2720,24 @@ fn value_strategy() -> impl Strategy<Value = Value> {
 
 fn map_value_strategy() -> impl Strategy<Value = Value> {
     prop::collection::vec(
-        (identifier_strategy().prop_map(Into::into), value_strategy()),
+        (key_strategy().prop_map(Into::into), value_strategy()),
         0..5,
     )
-    .prop_map(|v| Value::Map(ThinVec::from(v)))
+    .prop_map(|v| Value::Map(v.into_iter().collect()))
 }
 
 proptest! {
     #[test]
     fn test_value_roundtrip_prop(val in map_value_strategy()) {
-        let wrapped = Value::Map(thin_vec![("root".into(), val.clone())]);
-        let serialized = to_string(&wrapped).unwrap();
+        let serialized = to_string(&val).unwrap();
+        let deserialized: Value = from_str(&serialized).unwrap();
+        assert_eq!(val, deserialized);
+    }
+
+    #[test]
+    fn test_value_roundtrip_auto_strings_prop(val in map_value_strategy()) {
+        let config = PrettyConfig::new().string_style(StringStyle::Auto);
+        let serialized = to_string_pretty(&val, config).unwrap();
         let deserialized: Value = from_str(&serialized).unwrap();
         assert_eq!(val, deserialized);
     }